@@ -0,0 +1,531 @@
+//! The optional HTTP API to monitor and control the bridge, e.g. when it runs without the GUI
+use std::{io::{ErrorKind, Read, Write}, net::{SocketAddr, TcpListener, TcpStream}, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, mpsc, Arc, Mutex}, thread::{self, JoinHandle}, time::{Duration, Instant}};
+
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use tungstenite::handshake::derive_accept_key;
+
+use crate::cli::{Arguments, LossPolicy};
+use crate::config::Config;
+use crate::metrics;
+use crate::output::OutputKind;
+use crate::runner::{self, RunnerUpdate, UPDATE_INTERVAL};
+use crate::stream::{self, Subscription};
+
+///How long a command waits for the runner owner to handle it
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+///How often the server checks for new connections and whether it should stop
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(10);
+///The most connections handled at once, every one has its own thread
+const MAX_CONNECTIONS: usize = 32;
+///A client has to send its whole request within this time
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+///The largest accepted request line and headers
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+///The largest accepted request body
+const MAX_BODY_SIZE: usize = 64 * 1024;
+///Reads of a stream time out after this long, so it can send the next frame in between
+const STREAM_READ_TIMEOUT: Duration = UPDATE_INTERVAL;
+///A client which stops reading can't hold its connection longer than this
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum ApiCommand {
+    Start,
+    Stop,
+    Restart,
+    Blackout(bool),
+    ///New settings, saved like the ones applied in the GUI
+    Configure(Box<Arguments>),
+}
+
+///A command waiting for its result
+pub struct ApiRequest {
+    pub command: ApiCommand,
+    pub reply: ApiReply,
+}
+
+///Answers the client of an [`ApiRequest`]
+pub struct ApiReply(mpsc::Sender<Result<(), String>>);
+
+impl ApiReply {
+    pub fn send(self, result: Result<(), String>) {
+        // The client might have given up already
+        let _ = self.0.send(result);
+    }
+}
+
+///What the server reports, published by the owner of the runner
+#[derive(Default)]
+pub struct ApiState {
+    arguments: Option<Arguments>,
+    running: bool,
+    update: Option<RunnerUpdate>,
+}
+
+///The running HTTP server, stops when dropped
+pub struct ApiServer {
+    state: Arc<Mutex<ApiState>>,
+    requests: mpsc::Receiver<ApiRequest>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ApiServer {
+    ///Listens on `address`, `wake` is called for every command (e.g. to repaint the GUI)
+    pub fn start(address: SocketAddr, wake: impl Fn() + Send + Sync + 'static) -> Result<Self, ApiError> {
+        let listener = TcpListener::bind(address)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|error| ApiError(address, error.to_string()))?;
+        info!("HTTP API listening on http://{}/api", address);
+        if !address.ip().is_loopback() {
+            warn!("The HTTP API is reachable from other machines on {}, without --http-token anyone there can control the bridge", address);
+        }
+        let state = Arc::new(Mutex::new(ApiState::default()));
+        let (tx, requests) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let state = state.clone();
+            let running = running.clone();
+            let wake: Arc<dyn Fn() + Send + Sync> = Arc::new(wake);
+            let connections = Limit::new(MAX_CONNECTIONS);
+            let clients = Limit::new(stream::MAX_CLIENTS);
+            thread::spawn(move || {
+                while running.load(Ordering::Relaxed) {
+                    let mut stream = match listener.accept() {
+                        Ok((stream, _)) => stream,
+                        Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                            thread::sleep(ACCEPT_INTERVAL);
+                            continue;
+                        },
+                        Err(error) => {
+                            runner::listener_error("HTTP API", error);
+                            continue;
+                        },
+                    };
+                    // Accepted connections inherit the nonblocking listener on some platforms
+                    if let Err(error) = stream.set_nonblocking(false)
+                        .and_then(|_| stream.set_read_timeout(Some(REQUEST_TIMEOUT)))
+                        .and_then(|_| stream.set_write_timeout(Some(WRITE_TIMEOUT))) {
+                        warn!("Couldn't set up HTTP connection: {}", error);
+                        continue;
+                    }
+                    let Some(connection) = connections.reserve() else {
+                        respond_error(&mut stream, 503, "Too many connections");
+                        continue;
+                    };
+                    let (state, running, tx, wake, clients) = (state.clone(), running.clone(), tx.clone(), wake.clone(), clients.clone());
+                    thread::spawn(move || {
+                        let _connection = connection;
+                        match read_request(&mut stream) {
+                            Ok(request) => handle(request, stream, &state, &running, &clients, &tx, &*wake),
+                            Err((status_code, message)) => respond_error(&mut stream, status_code, &message),
+                        }
+                    });
+                }
+            })
+        };
+        Ok(Self {
+            state,
+            requests,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    ///The commands recieved since the last call, every one has to be replied to
+    pub fn requests(&self) -> mpsc::TryIter<'_, ApiRequest> {
+        self.requests.try_iter()
+    }
+
+    pub fn set_arguments(&self, arguments: Option<&Arguments>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.arguments = arguments.cloned();
+        }
+    }
+
+    ///`update` is the latest one of the runner, `None` until it sent one
+    pub fn set_update(&self, running: bool, update: Option<RunnerUpdate>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.running = running;
+            state.update = update.filter(|_| running);
+        }
+    }
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+///Counts the places taken of a limited number, e.g. the connections of a server
+#[derive(Clone)]
+struct Limit {
+    taken: Arc<AtomicUsize>,
+    max: usize,
+}
+
+impl Limit {
+    fn new(max: usize) -> Self {
+        Self {
+            taken: Arc::new(AtomicUsize::new(0)),
+            max,
+        }
+    }
+
+    ///`None` if all places are taken
+    fn reserve(&self) -> Option<Slot> {
+        self.taken.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |taken| (taken < self.max).then_some(taken + 1)).ok()?;
+        Some(Slot(self.taken.clone()))
+    }
+}
+
+///A place of a [`Limit`], given back when dropped
+pub struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+}
+
+///Reads a request with its body, the connection is closed after answering it
+fn read_request(stream: &mut TcpStream) -> Result<Request, (u16, String)> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut buffer = Vec::new();
+    let (mut request, head_length) = loop {
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut request = httparse::Request::new(&mut headers);
+        match request.parse(&buffer) {
+            Ok(httparse::Status::Complete(length)) => {
+                let (path, query) = request.path.unwrap_or("/").split_once('?').unwrap_or((request.path.unwrap_or("/"), ""));
+                break (Request {
+                    method: request.method.unwrap_or_default().to_string(),
+                    path: path.trim_end_matches('/').to_string(),
+                    query: query.to_string(),
+                    headers: request.headers.iter().map(|header| (header.name.to_string(), String::from_utf8_lossy(header.value).into_owned())).collect(),
+                    body: Vec::new(),
+                }, length);
+            },
+            Ok(httparse::Status::Partial) if buffer.len() < MAX_HEAD_SIZE => fill(stream, &mut buffer, deadline)?,
+            Ok(httparse::Status::Partial) | Err(httparse::Error::TooManyHeaders) => return Err((431, "Request header too large".into())),
+            Err(error) => return Err((400, format!("Invalid request: {}", error))),
+        }
+    };
+    if request.header("Transfer-Encoding").is_some() {
+        return Err((411, "Expected a Content-Length".into()));
+    }
+    let length = match request.header("Content-Length").map(str::parse::<usize>) {
+        Some(Ok(length)) if length > MAX_BODY_SIZE => return Err((413, format!("The body can't be larger than {} bytes", MAX_BODY_SIZE))),
+        Some(Ok(length)) => length,
+        Some(Err(_)) => return Err((400, "Invalid Content-Length".into())),
+        None => 0,
+    };
+    if length > 0 && request.header("Expect").is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue")) {
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").map_err(|error| (400, error.to_string()))?;
+    }
+    while buffer.len() < head_length + length {
+        fill(stream, &mut buffer, deadline)?;
+    }
+    request.body = buffer[head_length..head_length + length].to_vec();
+    Ok(request)
+}
+
+///Reads the next part of a request into `buffer`
+fn fill(stream: &mut TcpStream, buffer: &mut Vec<u8>, deadline: Instant) -> Result<(), (u16, String)> {
+    if Instant::now() >= deadline {
+        return Err((408, "Request timed out".into()));
+    }
+    let mut chunk = [0; 4096];
+    match stream.read(&mut chunk) {
+        Ok(0) => Err((400, "Incomplete request".into())),
+        Ok(length) => {
+            buffer.extend_from_slice(&chunk[..length]);
+            Ok(())
+        },
+        Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Err((408, "Request timed out".into())),
+        Err(error) => Err((400, error.to_string())),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status_code: u16, content_type: &str, body: &str) {
+    let reason = match status_code {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        411 => "Length Required",
+        413 => "Content Too Large",
+        426 => "Upgrade Required",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    };
+    let headers = match status_code {
+        401 => "WWW-Authenticate: Bearer\r\n",
+        426 => "Upgrade: websocket\r\nSec-WebSocket-Version: 13\r\n",
+        _ => "",
+    };
+    let response = format!("HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}", status_code, reason, content_type, body.len(), headers, body);
+    if let Err(error) = stream.write_all(response.as_bytes()) {
+        warn!("Couldn't answer HTTP request: {}", error);
+    }
+}
+
+fn respond_error(stream: &mut TcpStream, status_code: u16, message: &str) {
+    respond(stream, status_code, "application/json", &json!({ "error": message }).to_string());
+}
+
+fn handle(request: Request, mut stream: TcpStream, state: &Arc<Mutex<ApiState>>, running: &Arc<AtomicBool>, clients: &Limit, tx: &mpsc::Sender<ApiRequest>, wake: &dyn Fn()) {
+    debug!("HTTP {} {}", request.method, request.path);
+    let token = state.lock().ok().and_then(|state| state.arguments.as_ref().and_then(|arguments| arguments.options.http_token.clone()));
+    if token.is_some_and(|token| !authorized(&request, &token)) {
+        respond_error(&mut stream, 401, "Missing or wrong bearer token");
+        return;
+    }
+    let response = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/metrics") => {
+            let text = match state.lock() {
+                Ok(state) => metrics::render(state.running, state.update.as_ref(), state.arguments.as_ref()),
+                Err(_) => String::new(),
+            };
+            respond(&mut stream, 200, metrics::CONTENT_TYPE, &text);
+            return;
+        },
+        ("GET", "/api/stream") => match websocket_key(&request).and_then(|key| Ok((key, request.query.parse::<Subscription>().map_err(|error| (400, error))?))) {
+            Ok((key, subscription)) => match clients.reserve() {
+                Some(client) => {
+                    let response = format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", derive_accept_key(key.as_bytes()));
+                    match stream.write_all(response.as_bytes()).and_then(|_| stream.set_read_timeout(Some(STREAM_READ_TIMEOUT))) {
+                        Ok(()) => stream::serve(stream, client, subscription, state, running),
+                        Err(error) => warn!("Couldn't answer HTTP request: {}", error),
+                    }
+                    return;
+                },
+                None => Err((503, format!("Too many stream clients (at most {})", stream::MAX_CLIENTS))),
+            },
+            Err(error) => Err(error),
+        },
+        ("GET", "/api/status") => read(state, |state| Ok(state.status())),
+        ("GET", "/api/frame") => read(state, |state| Ok(frame(state))),
+        ("GET", "/api/config") => read(state, |state| match &state.arguments {
+            Some(arguments) => {
+                let mut config = json!(Config::from(arguments));
+                config.as_object_mut().map(|config| config.remove("http_token"));
+                Ok(config)
+            },
+            None => Err((404, "No settings yet".into())),
+        }),
+        ("PUT", "/api/config") => body(&request)
+            .and_then(|body| configure(&body, state))
+            .and_then(|arguments| command(ApiCommand::Configure(Box::new(arguments)), tx, wake)),
+        ("POST", "/api/start") => command(ApiCommand::Start, tx, wake),
+        ("POST", "/api/stop") => command(ApiCommand::Stop, tx, wake),
+        ("POST", "/api/restart") => command(ApiCommand::Restart, tx, wake),
+        ("POST", "/api/blackout") => body(&request)
+            .and_then(|body| match body.get("enabled").and_then(Value::as_bool) {
+                Some(enabled) => Ok(enabled),
+                None => Err((400, "Expected {\"enabled\": true|false}".into())),
+            })
+            .and_then(|enabled| command(ApiCommand::Blackout(enabled), tx, wake)),
+        (_, "/metrics" | "/api/status" | "/api/stream" | "/api/frame" | "/api/config" | "/api/start" | "/api/stop" | "/api/restart" | "/api/blackout") => Err((405, "Method not allowed".into())),
+        _ => Err((404, "Not found".into())),
+    };
+    match response {
+        Ok(value) => respond(&mut stream, 200, "application/json", &value.to_string()),
+        Err((status_code, message)) => respond_error(&mut stream, status_code, &message),
+    }
+}
+
+type ApiResult = Result<Value, (u16, String)>;
+
+///Whether `request` has the bearer token, compared in constant time
+fn authorized(request: &Request, token: &str) -> bool {
+    let given = request.header("Authorization").and_then(|value| value.strip_prefix("Bearer ")).unwrap_or_default();
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+///The key of a WebSocket handshake
+fn websocket_key(request: &Request) -> Result<String, (u16, String)> {
+    let upgrade = request.header("Upgrade").is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+    match request.header("Sec-WebSocket-Key") {
+        Some(_) if upgrade && request.header("Sec-WebSocket-Version").map(str::trim) != Some("13") => Err((426, "Only WebSocket version 13 is supported".into())),
+        Some(key) if upgrade => Ok(key.to_string()),
+        _ => Err((426, "Expected a WebSocket connection".into())),
+    }
+}
+
+fn read(state: &Mutex<ApiState>, f: impl FnOnce(&ApiState) -> ApiResult) -> ApiResult {
+    match state.lock() {
+        Ok(state) => f(&state),
+        Err(_) => Err((500, "State unavailable".into())),
+    }
+}
+
+fn body(request: &Request) -> Result<Value, (u16, String)> {
+    serde_json::from_slice(&request.body).map_err(|error| (400, format!("Invalid JSON: {}", error)))
+}
+
+///Hands `command` to the owner of the runner and waits for the result
+fn command(command: ApiCommand, tx: &mpsc::Sender<ApiRequest>, wake: &dyn Fn()) -> ApiResult {
+    let (reply, result) = mpsc::channel();
+    if tx.send(ApiRequest { command, reply: ApiReply(reply) }).is_err() {
+        return Err((503, "Bridge shutting down".into()));
+    }
+    wake();
+    match result.recv_timeout(COMMAND_TIMEOUT) {
+        Ok(Ok(())) => Ok(json!({ "ok": true })),
+        Ok(Err(message)) => Err((409, message)),
+        Err(_) => Err((503, "Bridge didn't respond".into())),
+    }
+}
+
+///The new settings, the options which aren't part of the config file stay as they are
+fn configure(body: &Value, state: &Mutex<ApiState>) -> Result<Arguments, (u16, String)> {
+    let config = serde_json::from_value::<Config>(body.clone()).map_err(|error| (400, error.to_string()))?;
+    let mut arguments = Arguments::try_from(config).map_err(|error| (400, error.to_string()))?;
+    if arguments.routes.is_empty() {
+        return Err((400, "No routes configured".into()));
+    }
+    if let Ok(state) = state.lock() {
+        check_remote(&arguments, state.arguments.as_ref())?;
+        if let Some(current) = &state.arguments {
+            arguments.options.record = current.options.record.clone();
+            arguments.options.playback = current.options.playback.clone();
+            arguments.options.verbose = current.options.verbose;
+            arguments.options.config = current.options.config.clone();
+            arguments.options.http_token = current.options.http_token.clone();
+        }
+    }
+    Ok(arguments)
+}
+
+///Fails if `arguments` change settings which reach beyond the bridge: file and terminal outputs,
+///scenes (both paths on this machine) and the address of the API itself. Those stay local, the API may only keep them.
+fn check_remote(arguments: &Arguments, current: Option<&Arguments>) -> Result<(), (u16, String)> {
+    for route in &arguments.routes {
+        let kind = route.output.unwrap_or(arguments.options.output);
+        let unchanged = current.is_some_and(|current| current.routes.iter().any(|old| old.device_name == route.device_name && old.output.unwrap_or(current.options.output) == kind));
+        if matches!(kind, OutputKind::File | OutputKind::Pty) && !unchanged {
+            return Err((403, format!("{} outputs can't be set up over the API", kind)));
+        }
+    }
+    if matches!(arguments.options.loss, LossPolicy::Scene(_)) && current.map(|current| &current.options.loss) != Some(&arguments.options.loss) {
+        return Err((403, "Scenes can't be set up over the API".into()));
+    }
+    if current.map(|current| current.options.http) != Some(arguments.options.http) {
+        return Err((403, "The address of the API can't be changed over the API".into()));
+    }
+    Ok(())
+}
+
+impl ApiState {
+    ///The body of `/api/status`
+    pub fn status(&self) -> Value {
+        let Some(update) = &self.update else {
+            return json!({ "running": self.running });
+        };
+        let statistics = &update.statistics;
+        json!({
+            "running": self.running,
+            "connected_to_artnet": update.connected_to_artnet,
+            "synchronous": update.synchronous,
+            "recording": update.recording,
+            "blackout": update.blackout,
+            "master": update.master,
+            "playback": update.playback.map(|playback| json!({
+                "state": playback.state.to_string(),
+                "position": playback.position.as_secs_f32(),
+                "length": playback.length.as_secs_f32(),
+            })),
+            "routes": update.routes.iter().map(|route| json!({
+                "universe": route.universe,
+                "device": route.device.to_string(),
+                "sources": route.sources,
+                "signal_lost": route.signal_lost,
+                "held_channels": route.held_channels,
+                "last_sender": route.dmx_recieved.map(|sender| sender.to_string()),
+            })).collect::<Vec<_>>(),
+            "statistics": {
+                "packets_per_second": statistics.packets_per_second,
+                "frames_per_second": statistics.frames_per_second,
+                "packets": statistics.packets,
+                "frames": statistics.frames,
+                "other_universe_packets": statistics.other_universe_packets,
+                "rejected_packets": statistics.rejected_packets,
+                "serial_failures": statistics.serial_failures,
+                "reconnects": statistics.reconnects,
+                "senders": statistics.senders.iter().map(|sender| json!({
+                    "ip": sender.ip.to_string(),
+                    "protocol": sender.protocol.to_string(),
+                    "packets": sender.packets,
+                    "packets_per_second": sender.packets_per_second,
+                    "rejected": sender.rejected,
+                })).collect::<Vec<_>>(),
+            },
+        })
+    }
+
+    ///The universe and channels of every route, empty while the runner is stopped
+    pub fn frames(&self) -> Vec<(u16, [u8; 512])> {
+        self.update.iter().flat_map(|update| update.routes.iter()).map(|route| (route.universe, route.channels)).collect()
+    }
+}
+
+fn frame(state: &ApiState) -> Value {
+    let routes = state.frames().into_iter().map(|(universe, channels)| json!({
+        "universe": universe,
+        "channels": channels.to_vec(),
+    })).collect::<Vec<_>>();
+    json!({ "routes": routes })
+}
+
+#[derive(Debug)]
+pub struct ApiError(SocketAddr, String);
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Couldn't start HTTP API on {}: {}", self.0, self.1)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gives_back_places_of_a_limit() {
+        let limit = Limit::new(2);
+        let slots = std::iter::from_fn(|| limit.reserve()).take(3).collect::<Vec<_>>();
+        assert_eq!(slots.len(), 2);
+        // Another server has its own places
+        assert!(Limit::new(2).reserve().is_some());
+        drop(slots);
+        assert!(limit.reserve().is_some());
+    }
+}
@@ -0,0 +1,372 @@
+//! The art-net listener of the bridge
+use std::{io, net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket}, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, mpsc, Arc, Mutex}, thread::{self, JoinHandle}};
+
+use artnet_protocol::{ArtCommand, PollReply};
+use socket2::Socket;
+use log::{debug, warn};
+
+use crate::cli::MergeMode;
+use crate::filter::SenderFilter;
+use crate::network::{self, NetworkInterface};
+use crate::runner::{self, InputEvent, InputFrame, Protocol, Rejections, LISTENER_TIMEOUT};
+
+///GoodOutput: Data is being transmitted
+pub const GOOD_OUTPUT_DATA: u8 = 0b1000_0000;
+///GoodOutput: Output is merging art-net data
+pub const GOOD_OUTPUT_MERGING: u8 = 0b0000_1000;
+///GoodOutput: Merge mode is LTP
+pub const GOOD_OUTPUT_LTP: u8 = 0b0000_0010;
+
+///The art-net port, which poll replies have to report regardless of the port we listen on
+const ARTNET_PORT: u16 = 6454;
+///PortTypes: The port can output DMX512 data from the art-net network
+const PORT_TYPE_DMX_OUTPUT: u8 = 0b1000_0000;
+///GoodInput: The input is disabled
+const GOOD_INPUT_DISABLED: u8 = 0b0000_1000;
+///Status1: Indicators in normal mode, Port-Addresses set by the node's configuration
+const STATUS_1: u8 = 0b1101_0000;
+///Status2: The node supports 15-bit Port-Addresses
+const STATUS_2: u8 = 0b0000_1000;
+///The OEM code for nodes without a registered code
+const OEM_UNKNOWN: [u8; 2] = [0x00, 0xff];
+///NodeReport: Power on tests successful
+const REPORT_POWER_OK: u16 = 0x0001;
+const OP_ADDRESS: u16 = 0x6000;
+const ADDRESS_PACKET_LENGTH: usize = 107;
+
+///The state of the node which is reported in poll replies
+#[derive(Debug, Clone, Default)]
+pub struct NodeInfo {
+    pub short_name: String,
+    pub long_name: String,
+    pub ports: Vec<PortInfo>,
+}
+
+///The state of one output port (route) of the node
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PortInfo {
+    pub universe: u16,
+    pub good_output: u8,
+}
+
+pub type SharedNodeInfo = Arc<Mutex<NodeInfo>>;
+
+///The network interface the node is reachable on
+#[derive(Debug, Clone, Copy)]
+pub struct Interface {
+    pub ip: Ipv4Addr,
+    pub mac: [u8; 6],
+    ///Where the node announces itself
+    pub broadcast: Ipv4Addr,
+}
+
+impl Interface {
+    ///Finds the interface behind `ip`, or the default interface if `ip` is unspecified
+    pub fn find(ip: Ipv4Addr) -> Self {
+        let ip = match ip.is_unspecified() {
+            true => match local_ip_address::local_ip() {
+                Ok(IpAddr::V4(ip)) => ip,
+                _ => ip,
+            },
+            false => ip,
+        };
+        let interface = network::find_by_ip(ip);
+        let mac = interface.as_ref()
+            .and_then(|interface| mac_address::mac_address_by_name(&interface.name).ok().flatten())
+            .map(|mac| mac.bytes())
+            .unwrap_or_default();
+        let broadcast = interface.as_ref().map(NetworkInterface::broadcast).unwrap_or(Ipv4Addr::BROADCAST);
+        Self { ip, mac, broadcast }
+    }
+}
+
+impl NodeInfo {
+    ///Builds one reply per port, `replies` is the number of replies sent before
+    pub fn poll_replies(&self, interface: &Interface, replies: usize) -> Vec<PollReply> {
+        let mut short_name = [0; 18];
+        self.short_name.bytes().take(17).zip(short_name.iter_mut()).for_each(|(a, b)| *b = a);
+        let mut long_name = [0; 64];
+        self.long_name.bytes().take(63).zip(long_name.iter_mut()).for_each(|(a, b)| *b = a);
+        let mut node_report = [0; 64];
+        format!("#{:04x} [{:04}] OK", REPORT_POWER_OK, replies % 10000).bytes().zip(node_report.iter_mut()).for_each(|(a, b)| *b = a);
+
+        let reply = |port: Option<&PortInfo>, bind_index: u8| {
+            let universe = port.map(|port| port.universe).unwrap_or_default();
+            PollReply {
+                address: interface.ip,
+                port: ARTNET_PORT,
+                version: [1, 0],
+                // Net (bits 14-8) and Sub-Net (bits 7-4), the universe nibble goes into swout
+                port_address: [(universe >> 8) as u8 & 0x7f, (universe >> 4) as u8 & 0x0f],
+                oem: OEM_UNKNOWN,
+                ubea_version: 0,
+                status_1: STATUS_1,
+                esta_code: 0,
+                short_name,
+                long_name,
+                node_report,
+                num_ports: [0, port.is_some() as u8],
+                port_types: [if port.is_some() { PORT_TYPE_DMX_OUTPUT } else { 0 }, 0, 0, 0],
+                good_input: [GOOD_INPUT_DISABLED, 0, 0, 0],
+                good_output: [port.map(|port| port.good_output).unwrap_or_default(), 0, 0, 0],
+                swin: [0; 4],
+                swout: [universe as u8 & 0x0f, 0, 0, 0],
+                sw_video: 0,
+                sw_macro: 0,
+                sw_remote: 0,
+                style: 0x00, // StNode
+                mac: interface.mac,
+                bind_ip: interface.ip.octets(),
+                bind_index,
+                status_2: STATUS_2,
+                filler: [0; 26],
+                spare: [0; 3],
+            }
+        };
+        match self.ports.is_empty() {
+            true => vec![reply(None, 1)],
+            false => self.ports.iter().zip(1..=u8::MAX).map(|(port, bind_index)| reply(Some(port), bind_index)).collect(),
+        }
+    }
+}
+
+///A recieved ArtAddress packet, names which are `None` stay unchanged
+#[derive(Debug, Clone)]
+pub struct ArtAddress {
+    ///The bound node (route) the packet is meant for, starting at 1
+    pub bind_index: u8,
+    pub net_switch: Switch,
+    pub sub_switch: Switch,
+    pub sw_out: Switch,
+    pub short_name: Option<String>,
+    pub long_name: Option<String>,
+    pub command: AddressCommand,
+}
+
+///A part of the Port-Address set by ArtAddress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Switch {
+    Unchanged,
+    ///Back to the universe the route started with
+    Reset,
+    Program(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressCommand {
+    None,
+    CancelMerge,
+    Merge(MergeMode),
+    ///Commands the node doesn't support
+    Other(u8),
+}
+
+impl ArtAddress {
+    pub fn parse(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < ADDRESS_PACKET_LENGTH || &buffer[..8] != b"Art-Net\0" || u16::from_le_bytes([buffer[8], buffer[9]]) != OP_ADDRESS {
+            return None;
+        }
+        // Switches are only programmed if their top bit is set, 0 resets them
+        let switch = |value: u8, mask: u8| match value {
+            0x00 => Switch::Reset,
+            value if value & 0x80 != 0 => Switch::Program(value & mask),
+            _ => Switch::Unchanged,
+        };
+        let name = |bytes: &[u8]| {
+            let end = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+            let name = String::from_utf8_lossy(&bytes[..end]).into_owned();
+            (!name.is_empty()).then_some(name)
+        };
+        Some(Self {
+            net_switch: switch(buffer[12], 0x7f),
+            bind_index: buffer[13],
+            short_name: name(&buffer[14..32]),
+            long_name: name(&buffer[32..96]),
+            sw_out: switch(buffer[100], 0x0f),
+            sub_switch: switch(buffer[104], 0x0f),
+            command: match buffer[106] {
+                0x00 => AddressCommand::None,
+                0x01 => AddressCommand::CancelMerge,
+                0x10 => AddressCommand::Merge(MergeMode::Ltp),
+                0x50 => AddressCommand::Merge(MergeMode::Htp),
+                command => AddressCommand::Other(command),
+            },
+        })
+    }
+
+    ///Applies the Net, Sub-Net and universe switches to the 15-bit Port-Address `universe`,
+    ///resetting them to `initial`
+    pub fn universe(&self, universe: u16, initial: u16) -> u16 {
+        let field = |switch: Switch, shift: u16, mask: u16| match switch {
+            Switch::Unchanged => universe >> shift & mask,
+            Switch::Reset => initial >> shift & mask,
+            Switch::Program(value) => u16::from(value),
+        };
+        field(self.net_switch, 8, 0x7f) << 8 | field(self.sub_switch, 4, 0x0f) << 4 | field(self.sw_out, 0, 0x0f)
+    }
+}
+
+///Sends the poll replies of the node
+///
+///Shared between the listener, which answers ArtPoll, and the runner, which answers ArtAddress.
+#[derive(Clone)]
+pub struct Responder {
+    socket: Arc<UdpSocket>,
+    interface: Interface,
+    node: SharedNodeInfo,
+    replies: Arc<AtomicUsize>,
+}
+
+impl Responder {
+    ///Sends the poll replies to the broadcast address of the interface, so controllers see the node right away
+    pub fn announce(&self) {
+        self.reply(SocketAddr::from((self.interface.broadcast, ARTNET_PORT)));
+    }
+
+    pub fn reply(&self, to: SocketAddr) {
+        let poll_replies = match self.node.lock() {
+            Ok(node) => node.poll_replies(&self.interface, self.replies.fetch_add(1, Ordering::Relaxed)),
+            Err(_) => return,
+        };
+        for reply in poll_replies {
+            let reply_bytes = match ArtCommand::PollReply(Box::new(reply)).write_to_buffer() {
+                Ok(bytes) => bytes,
+                Err(error) => {
+                    warn!("Couldn't write poll reply: {}", error);
+                    continue;
+                },
+            };
+            if let Err(error) = self.socket.send_to(&reply_bytes, to) {
+                warn!("Couldn't send poll reply to {}: {}", to, error);
+            }
+        }
+    }
+}
+
+///Starts a thread listening on `address` (and only on `device` if given) and forwarding the art-net
+///output of the allowed `controller`s (default: all) to `tx`
+pub fn start_reciever(mut address: SocketAddrV4, device: Option<&NetworkInterface>, controller: Option<SenderFilter>, node: SharedNodeInfo, tx: mpsc::Sender<InputEvent>, running: Arc<AtomicBool>) -> io::Result<(Responder, JoinHandle<()>)> {
+    let socket = Socket::new(socket2::Domain::IPV4, socket2::Type::DGRAM, Some(socket2::Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.set_broadcast(true)?;
+    if let Some(device) = device {
+        // Without binding to the device, broadcasts only arrive on the unspecified address
+        if !network::bind_device(&socket, device) && address.ip().is_unspecified() {
+            address.set_ip(device.ip);
+        }
+    }
+    socket.bind(&SocketAddr::from(address).into())?;
+    let socket = Arc::new(UdpSocket::from(socket));
+    socket.set_read_timeout(Some(LISTENER_TIMEOUT))?;
+    let interface = Interface::find(device.map_or(*address.ip(), |device| device.ip));
+    debug!("Reporting art-net node on {} ({:02x?}), broadcasting to {}", interface.ip, interface.mac, interface.broadcast);
+    let responder = Responder {
+        socket: socket.clone(),
+        interface,
+        node,
+        replies: Arc::new(AtomicUsize::new(0)),
+    };
+
+    responder.announce();
+
+    let listener_responder = responder.clone();
+    let thread = thread::spawn(move || {
+        let responder = listener_responder;
+        let mut buffer = [0; 1024];
+        let mut rejections = Rejections::new(Protocol::Artnet);
+        while running.load(Ordering::Relaxed) {
+            if rejections.report(&tx).is_err() {
+                debug!("Art-net reciever disconnected");
+                break;
+            }
+            let (size, sender) = match socket.recv_from(&mut buffer) {
+                Ok(packet) => packet,
+                Err(error) => {
+                    runner::listener_error("Art-net", error);
+                    continue;
+                },
+            };
+            let command = match ArtCommand::from_buffer(&buffer[..size]) {
+                Ok(command) => command,
+                Err(_) => continue,
+            };
+            // Other controllers can still discover the node
+            if controller.as_ref().is_some_and(|controller| !controller.allows(sender.ip())) && !matches!(command, ArtCommand::Poll(_)) {
+                rejections.count(sender.ip());
+                continue;
+            }
+            match command {
+                ArtCommand::Poll(_) => responder.reply(sender),
+                ArtCommand::Output(output) => {
+                    let mut channels = [0; 512];
+                    output.data.as_ref().iter().zip(channels.iter_mut()).for_each(|(a, b)| *b = *a);
+                    let frame = InputFrame {
+                        sender,
+                        universe: output.port_address.into(),
+                        channels,
+                        protocol: Protocol::Artnet,
+                    };
+                    if tx.send(InputEvent::Dmx(Box::new(frame))).is_err() {
+                        debug!("Art-net reciever disconnected");
+                        break;
+                    }
+                },
+                ArtCommand::Sync => {
+                    let result = tx.send(InputEvent::Sync(sender));
+                    if result.is_err() {
+                        debug!("Art-net reciever disconnected");
+                        break;
+                    }
+                },
+                ArtCommand::Address => {
+                    let address = match ArtAddress::parse(&buffer[..size]) {
+                        Some(address) => address,
+                        None => continue,
+                    };
+                    if tx.send(InputEvent::Address(sender, Box::new(address))).is_err() {
+                        debug!("Art-net reciever disconnected");
+                        break;
+                    }
+                },
+                _ => {}, //unimplemented commands
+            }
+        }
+    });
+    Ok((responder, thread))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(net_switch: u8, sub_switch: u8, sw_out: u8, command: u8) -> ArtAddress {
+        let mut buffer = [0; ADDRESS_PACKET_LENGTH];
+        buffer[..8].copy_from_slice(b"Art-Net\0");
+        buffer[8..10].copy_from_slice(&OP_ADDRESS.to_le_bytes());
+        buffer[12] = net_switch;
+        buffer[13] = 2;
+        buffer[14..18].copy_from_slice(b"Desk");
+        buffer[100] = sw_out;
+        buffer[104] = sub_switch;
+        buffer[106] = command;
+        ArtAddress::parse(&buffer).unwrap()
+    }
+
+    #[test]
+    fn parses_address_packets() {
+        let address = address(0x7f, 0x7f, 0x7f, 0x10);
+        assert_eq!(address.bind_index, 2);
+        assert_eq!(address.short_name.as_deref(), Some("Desk"));
+        assert_eq!(address.long_name, None);
+        assert_eq!(address.command, AddressCommand::Merge(MergeMode::Ltp));
+        assert_eq!(address.universe(0x123, 0), 0x123);
+    }
+
+    #[test]
+    fn programs_and_resets_switches() {
+        assert_eq!(address(0x81, 0x82, 0x83, 0).universe(0x123, 0), 0x123);
+        assert_eq!(address(0x7f, 0x85, 0x7f, 0).universe(0x123, 0), 0x153);
+        // Only the reset switches go back to the initial universe
+        assert_eq!(address(0x7f, 0x00, 0x00, 0).universe(0x153, 0x204), 0x104);
+    }
+}
@@ -1,49 +1,142 @@
-use std::{env, time::Duration};
+use std::{env, net::{Ipv4Addr, SocketAddr}, path::PathBuf, time::Duration};
 use artnet_protocol::PortAddress;
+use chrono::NaiveTime;
+
+use crate::config;
+use crate::filter::SenderFilter;
+use crate::output::OutputKind;
+use crate::patch::Patch;
 
 pub const HELP_TEXT: &str =
 "A simple artnet to opendmx bridge
 
 Usage: artnet_to_opendmx.exe <UNIVERSE> <DEVICE_NAME> [OPTIONS]
+       artnet_to_opendmx.exe record <FILE> <UNIVERSES> [OPTIONS]
+       artnet_to_opendmx.exe play <FILE> <UNIVERSE> <DEVICE_NAME> [OPTIONS]
+       artnet_to_opendmx.exe [OPTIONS]   (runs the routes of the config file)
        artnet_to_opendmx.exe <COMMAND>
 
 Commands:
-  list    List available devices
+  list    List available devices and network interfaces (--probe asks USB devices if they are Pro widgets)
+  record  Record the universes (e.g. 0,1,4-7) to a show file without a device, until Ctrl-C
+  play    Play a show file to the devices (listens to nothing unless --input is given)
   help    Print this message
   version Print version
 
 Arguments:
   <UNIVERSE>     The art-net universe to listen to
-  <DEVICE_NAME>  The interface port name
+  <DEVICE_NAME>  The interface port name, optionally prefixed with the kind of output (e.g. pro:COM4)
+                 or a USB device as usb:<VID>:<PID>[:serial=<SERIAL>] (e.g. usb:0403:6001:serial=AB12CD)
 
 Options:
-  -c  --controller A specific controller to listen to (localhost is 0.0.0.0)  (default: all)
+  -a  --route      An additional route in the form <UNIVERSE>:<DEVICE_NAME>   (repeatable)
+  -i  --input      The protocol to listen to (artnet, sacn, both or none)     (default: artnet)
+  -c  --controller Only accept these controllers (e.g. 2.0.0.10,2.0.1.0/24)   (default: all)
+      --interface  The network interface to listen on (e.g. eth0)             (default: all)
+      --bind-ip    The local address to listen on (e.g. 127.0.0.1)            (default: 0.0.0.0)
   -p  --port       The port to listen to                                      (default: 6454)
+  -m  --merge      How to merge multiple senders on a universe (htp or ltp)   (default: htp)
   -n  --name       The name of the node
+      --short-name The short name of the node (max. 17 characters)            (default: artnet2opendmx)
   -b  --break      The minimum time in milliseconds between two dmx packets   (default: 25)
-  -r  --remember   Keep the last dmx values if the art-net connection is lost (default: false)
+  -l  --loss       What to do if the signal is lost (see below)               (default: off)
+  -r  --remember   Keep the last dmx values if the signal is lost (same as --loss hold)
+  -o  --output     The kind of dmx output (see below)                         (default: serial)
+      --patch      A file with channel patch rules (<INPUT> -> <OUTPUTS>)
+      --record     Record the merged input to a show file
+      --config     The config file, options given here override it             (default: per user)
+      --osc        Listen to OSC control messages on this port (e.g. 8000)    (default: off)
+      --http       Serve the HTTP API on this address (e.g. 127.0.0.1:8080)   (default: off)
+                   It controls the whole bridge, keep it on localhost or set a token
+      --http-token Only accept API requests with this bearer token           (default: none)
       --verbose    Print information about the received art-net packets       (default: false)
-      --nogui      Disable the GUI                                            (default: false)";
+      --nogui      Disable the GUI                                            (default: false)
+
+Playback options (play only):
+      --loop       Start over at the end of the show                          (default: false)
+      --offset     Where to start in the show in seconds                      (default: 0)
+      --speed      The playback speed factor                                  (default: 1)
+      --schedule   Only play in daily time windows (e.g. 18:00-23:30,6:00-8:00)
+
+Loss policies (the signal counts as lost after 4 seconds without data):
+  off              Stop refreshing the output and leave it to the fixtures
+  hold             Hold the last values forever
+  blackout:<SECS>  Hold the last values for <SECS> seconds, then blackout
+  fade:<SECS>      Fade the last values to black over <SECS> seconds
+  scene:<FILE>     Switch to the failsafe scene in <FILE> (<CHANNELS> = <VALUE> per line)
+
+Outputs:
+  serial           An Open DMX interface at <DEVICE_NAME>
+  pro              An Enttec DMX USB Pro (or compatible) widget at <DEVICE_NAME>
+  null             Discard the output (for dry runs)
+  file             Append every changed frame as 512 raw bytes to the file <DEVICE_NAME>
+  pty              Write the serial data to a pseudo-terminal linked at <DEVICE_NAME> (unix only)";
 
 ///A tool for controlling an open dmx interface via art-net
 #[derive(Debug)]
 pub enum Command {
-    List,
+    ///Lists the devices, probing USB devices for Pro widgets if `probe` is set
+    List { probe: bool },
     Help,
     Version,
+    Record(RecordArguments),
     Cli(Arguments),
     Gui(Option<Arguments>),
 }
 
 impl Command {
     pub fn parse() -> Result<Self, Box<dyn std::error::Error>> {
-        let args = env::args().collect::<Vec<String>>();
+        let mut args = env::args().collect::<Vec<String>>();
+        let _ = args.remove(0); //remove the first argument (the program name)
+        let explicit_config = match args.iter().position(|arg| arg == "--config") {
+            Some(index) if index + 1 < args.len() => {
+                let path = PathBuf::from(args.remove(index + 1));
+                args.remove(index);
+                Some(path)
+            },
+            Some(_) => return Err("Not enough arguments".into()),
+            None => None,
+        };
+        // The config is only loaded by commands which use it
+        let config_path = explicit_config.clone().or_else(config::default_path);
+        let load_config = || -> Result<Arguments, Box<dyn std::error::Error>> {
+            let mut arguments = match &config_path {
+                Some(path) => config::load(path)?.unwrap_or_default(),
+                None => Arguments::default(),
+            };
+            arguments.options.config = config_path.clone();
+            Ok(arguments)
+        };
         let mut args = args.into_iter();
-        let _ = args.next(); //remove the first argument (the program name)
         let command = match args.next() {
             Some(command) => command,
-            None => return Ok(Self::Gui(None)),
+            // The GUI loads the default config on its own
+            None if explicit_config.is_none() => return Ok(Self::Gui(None)),
+            None => String::new(),
         };
+        if command.is_empty() || (command.starts_with('-') && !["-L", "-l", "--list", "-H", "-h", "--help", "-V", "-v", "--version"].contains(&command.as_str())) {
+            //Run the routes of the config
+            let config = load_config()?;
+            let mut routes = config.routes;
+            let args = std::iter::once(command).filter(|arg| !arg.is_empty()).chain(args).collect::<Vec<_>>().into_iter();
+            let ParsedOptions { options, gui, .. } = parse_options(args, &mut routes, config.options)?;
+            if routes.is_empty() {
+                return Err("No routes configured".into());
+            }
+            check_routes(&routes)?;
+            if options.playback.is_some() {
+                return Err("Playback options need the play command".into());
+            }
+            let args = Arguments {
+                routes,
+                options,
+            };
+            return Ok(if gui {
+                Self::Gui(Some(args))
+            } else {
+                Self::Cli(args)
+            });
+        }
         if command.parse::<u16>().is_ok() {
             //Default Command
             if args.len() < 1 {
@@ -51,59 +144,14 @@ impl Command {
             }
             _ = PortAddress::try_from(command.parse::<u16>()?)?;
             let universe = command.parse::<u16>()?;
-            let device_name = args.next().unwrap();
-            //check for options
-            let mut options = Options::default();
-            let mut args2 = args.clone();
-            _ = args2.next();
-            let mut skip = false;
-            let mut gui = true;
-            for arg in args {
-                if skip {
-                    skip = false;
-                    continue;
-                }
-                match arg.as_str() {
-                    "-p" | "--port" => {
-                        if args2.len() < 1 {
-                            return Err("Not enough arguments".into());
-                        }
-                        options.port = Some(args2.next().unwrap().parse::<>()?);
-                        skip = true;
-                    },
-                    "-c" | "--controller" => {
-                        if args2.len() < 1 {
-                            return Err("Not enough arguments".into());
-                        }
-                        options.controller = Some(args2.next().unwrap());
-                        skip = true;
-                    },
-                    "-n" | "--name" => {
-                        if args2.len() < 1 {
-                            return Err("Not enough arguments".into());
-                        }
-                        options.name = Some(args2.next().unwrap());
-                        skip = true;
-                    },
-                    "-b" | "--break" => {
-                        if args2.len() < 1 {
-                            return Err("Not enough arguments".into());
-                        }
-                        options.break_time = Some(Duration::from_millis(args2.next().unwrap().parse::<u64>()?));
-                        skip = true;
-                    },
-                    "-r" | "--remember" => options.remember = true,
-                    "--verbose" => options.verbose = true,
-                    "--nogui" => gui = false,
-                    _ => {
-                        return Err(format!("Unknown option \"{arg}\"").into());
-                    }
-                }
-                args2.next();
+            let mut routes = vec![Route::new(universe, &args.next().unwrap())];
+            let ParsedOptions { options, gui, .. } = parse_options(args, &mut routes, load_config()?.options)?;
+            check_routes(&routes)?;
+            if options.playback.is_some() {
+                return Err("Playback options need the play command".into());
             }
             let args = Arguments {
-                universe,
-                device_name,
+                routes,
                 options,
             };
             return Ok(if gui {
@@ -114,7 +162,54 @@ impl Command {
         }
         //Other command
         match command.as_str() {
-            "list" | "-L" | "-l" | "--list" => Ok(Self::List),
+            "play" => {
+                if args.len() < 3 {
+                    return Err("Not enough arguments".into());
+                }
+                let path = PathBuf::from(args.next().unwrap());
+                let universe = args.next().unwrap().parse::<u16>()?;
+                _ = PortAddress::try_from(universe)?;
+                let mut routes = vec![Route::new(universe, &args.next().unwrap())];
+                let ParsedOptions { mut options, gui, given } = parse_options(args, &mut routes, load_config()?.options)?;
+                check_routes(&routes)?;
+                reject_options(&given, &["--record"], "play")?;
+                if !given.iter().any(|option| option == "-i" || option == "--input") {
+                    options.input = Input::None;
+                }
+                options.playback.get_or_insert_with(Playback::default).path = path;
+                let args = Arguments {
+                    routes,
+                    options,
+                };
+                Ok(if gui {
+                    Self::Gui(Some(args))
+                } else {
+                    Self::Cli(args)
+                })
+            },
+            "list" | "-L" | "-l" | "--list" => match args.next() {
+                None => Ok(Self::List { probe: false }),
+                Some(arg) if arg == "--probe" && args.len() == 0 => Ok(Self::List { probe: true }),
+                Some(arg) => Err(format!("Unknown option \"{arg}\"").into()),
+            },
+            "record" => {
+                if args.len() < 2 {
+                    return Err("Not enough arguments".into());
+                }
+                let path = PathBuf::from(args.next().unwrap());
+                let universes = parse_universes(&args.next().unwrap())?;
+                let mut routes = Vec::new();
+                let ParsedOptions { options, given, .. } = parse_options(args, &mut routes, load_config()?.options)?;
+                if !routes.is_empty() {
+                    return Err("Routes can't be used while recording".into());
+                }
+                reject_options(&given, &["-b", "--break", "-l", "--loss", "-r", "--remember", "-o", "--output", "--patch", "--record", "--osc", "--http", "--http-token", "--loop", "--offset", "--speed", "--schedule"], "record")?;
+                Ok(Self::Record(RecordArguments {
+                    path,
+                    universes,
+                    options,
+                }))
+            },
             "help" | "-H" | "-h" | "--help" => Ok(Self::Help),
             "version" | "-V" | "-v" | "--version" => Ok(Self::Version),
             _ => {
@@ -124,37 +219,589 @@ impl Command {
     }
 }
 
-#[derive(Debug, Clone)]
+///The options of a command
+struct ParsedOptions {
+    options: Options,
+    gui: bool,
+    ///The options given on the command line, as written
+    given: Vec<String>,
+}
+
+///Parses the options following the arguments of a command on top of `options` (from the config)
+fn parse_options(args: std::vec::IntoIter<String>, routes: &mut Vec<Route>, mut options: Options) -> Result<ParsedOptions, Box<dyn std::error::Error>> {
+    let mut args2 = args.clone();
+    _ = args2.next();
+    let mut skip = false;
+    let mut gui = true;
+    let mut given = Vec::new();
+    for arg in args {
+        if skip {
+            skip = false;
+            continue;
+        }
+        given.push(arg.clone());
+        match arg.as_str() {
+            "-p" | "--port" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.port = Some(args2.next().unwrap().parse::<>()?);
+                skip = true;
+            },
+            "-a" | "--route" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                routes.push(args2.next().unwrap().parse::<Route>()?);
+                skip = true;
+            },
+            "-i" | "--input" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.input = args2.next().unwrap().parse::<Input>()?;
+                skip = true;
+            },
+            "-c" | "--controller" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.controller = Some(args2.next().unwrap().parse::<SenderFilter>()?);
+                skip = true;
+            },
+            "--interface" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.interface = Some(args2.next().unwrap());
+                skip = true;
+            },
+            "--bind-ip" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.bind_ip = Some(args2.next().unwrap().parse::<Ipv4Addr>()?);
+                skip = true;
+            },
+            "-m" | "--merge" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.merge = args2.next().unwrap().parse::<MergeMode>()?;
+                skip = true;
+            },
+            "-n" | "--name" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.name = Some(args2.next().unwrap());
+                skip = true;
+            },
+            "--short-name" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.short_name = Some(args2.next().unwrap());
+                skip = true;
+            },
+            "-b" | "--break" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.break_time = Some(Duration::from_millis(args2.next().unwrap().parse::<u64>()?));
+                skip = true;
+            },
+            "-l" | "--loss" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.loss = args2.next().unwrap().parse::<LossPolicy>()?;
+                skip = true;
+            },
+            "-r" | "--remember" => options.loss = LossPolicy::Hold,
+            "-o" | "--output" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.output = args2.next().unwrap().parse::<OutputKind>()?;
+                skip = true;
+            },
+            "--patch" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.patch = Some(Patch::load(args2.next().unwrap())?);
+                skip = true;
+            },
+            "--record" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.record = Some(args2.next().unwrap().into());
+                skip = true;
+            },
+            "--loop" => options.playback.get_or_insert_with(Playback::default).looping = true,
+            "--offset" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.playback.get_or_insert_with(Playback::default).offset = Duration::try_from_secs_f32(args2.next().unwrap().parse::<f32>()?)?;
+                skip = true;
+            },
+            "--speed" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                let speed = args2.next().unwrap().parse::<f32>()?;
+                if !(speed > 0.0 && speed.is_finite()) {
+                    return Err(format!("Invalid speed \"{speed}\"").into());
+                }
+                options.playback.get_or_insert_with(Playback::default).speed = speed;
+                skip = true;
+            },
+            "--schedule" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.playback.get_or_insert_with(Playback::default).schedule = args2.next().unwrap().split(',').map(str::parse::<ScheduleWindow>).collect::<Result<_, _>>()?;
+                skip = true;
+            },
+            "--osc" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.osc = Some(args2.next().unwrap().parse::<u16>()?);
+                skip = true;
+            },
+            "--http" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.http = Some(args2.next().unwrap().parse::<SocketAddr>()?);
+                skip = true;
+            },
+            "--http-token" => {
+                if args2.len() < 1 {
+                    return Err("Not enough arguments".into());
+                }
+                options.http_token = Some(args2.next().unwrap()).filter(|token| !token.is_empty());
+                skip = true;
+            },
+            "--verbose" => options.verbose = true,
+            "--nogui" => gui = false,
+            _ => {
+                return Err(format!("Unknown option \"{arg}\"").into());
+            }
+        }
+        args2.next();
+    }
+    Ok(ParsedOptions {
+        options,
+        gui,
+        given,
+    })
+}
+
+///Fails if one of the `unsupported` options got used with `command`
+fn reject_options(given: &[String], unsupported: &[&str], command: &str) -> Result<(), String> {
+    match given.iter().find(|option| unsupported.contains(&option.as_str())) {
+        Some(option) => Err(format!("\"{option}\" can't be used with {command}")),
+        None => Ok(()),
+    }
+}
+
+///Parses a comma separated list of universes and universe ranges
+fn parse_universes(s: &str) -> Result<Vec<u16>, Box<dyn std::error::Error>> {
+    let mut universes = Vec::new();
+    for part in s.split(',') {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim().parse::<u16>()?, end.trim().parse::<u16>()?),
+            None => (part.trim().parse::<u16>()?, part.trim().parse::<u16>()?),
+        };
+        if start > end {
+            return Err(format!("Invalid universe range \"{}\"", part.trim()).into());
+        }
+        _ = PortAddress::try_from(end)?;
+        universes.extend(start..=end);
+    }
+    Ok(universes)
+}
+
+///Rejects routes sharing a device, they would fight over it
+pub fn check_routes(routes: &[Route]) -> Result<(), String> {
+    for (i, route) in routes.iter().enumerate() {
+        if routes[..i].iter().any(|other| other.device_name == route.device_name) {
+            return Err(format!("Device \"{}\" used twice", route.device_name));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Arguments {
+    ///The universe to device routes of the bridge
+    pub routes: Vec<Route>,
+    
+    pub options: Options,
+}
+
+///Records universes without forwarding them to a device
+#[derive(Debug, Clone)]
+pub struct RecordArguments {
+    ///The show file to write
+    pub path: PathBuf,
+    pub universes: Vec<u16>,
+    pub options: Options,
+}
+
+///Forwards one art-net universe to one open dmx interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
     ///The art-net universe to listen to
     pub universe: u16,
     ///The interface port name
     pub device_name: String,
-    
-    pub options: Options,
+    ///Overrides [`Options::output`] for this device
+    pub output: Option<OutputKind>,
 }
 
-impl Default for Arguments {
-    fn default() -> Self {
-        Self {
-            universe: 0,
-            device_name: "".into(),
-            options: Options::default(),
+impl Route {
+    ///Creates a route to `device`, which may start with the kind of output (e.g. `pro:COM4`)
+    pub fn new(universe: u16, device: &str) -> Self {
+        let (output, device_name) = match device.split_once(':') {
+            Some((kind, name)) if !name.is_empty() => match kind.parse::<OutputKind>() {
+                Ok(kind) => (Some(kind), name),
+                Err(_) => (None, device),
+            },
+            _ => (None, device),
+        };
+        Route {
+            universe,
+            device_name: device_name.into(),
+            output,
+        }
+    }
+}
+
+impl std::str::FromStr for Route {
+    type Err = Box<dyn std::error::Error>;
+
+    ///Parses a route in the form `<UNIVERSE>:[<OUTPUT>:]<DEVICE_NAME>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (universe, device_name) = s.split_once(':').ok_or_else(|| format!("Invalid route \"{s}\" (expected <UNIVERSE>:<DEVICE_NAME>)"))?;
+        let universe = universe.parse::<u16>()?;
+        _ = PortAddress::try_from(universe)?;
+        if device_name.is_empty() {
+            return Err(format!("Invalid route \"{s}\" (missing device name)").into());
+        }
+        Ok(Route::new(universe, device_name))
+    }
+}
+
+impl std::fmt::Display for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.output {
+            Some(output) => write!(f, "{}:{}:{}", self.universe, output.id(), self.device_name),
+            None => write!(f, "{}:{}", self.universe, self.device_name),
         }
     }
 }
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Options {
+    ///The protocol to listen to (default: artnet)
+    pub input: Input,
     ///The port to listen to (default: 6454)
     pub port: Option<u16>,
-    ///A specific controller to listen to (default: all)
-    pub controller: Option<String>,
+    ///The controllers to listen to (default: all)
+    pub controller: Option<SenderFilter>,
+    ///The network interface to listen on (default: all)
+    pub interface: Option<String>,
+    ///The local address to listen on (default: 0.0.0.0)
+    pub bind_ip: Option<Ipv4Addr>,
+    ///How to merge multiple senders on the same universe (default: htp)
+    pub merge: MergeMode,
     ///The name of the node
     pub name: Option<String>,
+    ///The short name of the node (default: artnet2opendmx)
+    pub short_name: Option<String>,
     ///The minimum time in milliseconds between two dmx packets (default: 25)
     pub break_time: Option<Duration>,
-    ///Keep the last dmx values if the art-net connection is lost (default: false)
-    pub remember: bool,
+    ///What to do if the signal is lost (default: off)
+    pub loss: LossPolicy,
+    ///Channel remapping between the recieved frame and the dmx output (default: none)
+    pub patch: Option<Patch>,
+    ///Where the dmx frames go (default: serial)
+    pub output: OutputKind,
+    ///A show file the dmx output gets recorded to (default: none)
+    pub record: Option<PathBuf>,
+    ///A show file played back as an additional source (default: none)
+    pub playback: Option<Playback>,
     ///Print information about the received art-net packets (default: false)
     pub verbose: bool,
-}
\ No newline at end of file
+    ///The config file the GUI saves its settings to
+    pub config: Option<PathBuf>,
+    ///The port the OSC controls are recieved on (default: off)
+    pub osc: Option<u16>,
+    ///The address the HTTP API listens on (default: off)
+    pub http: Option<SocketAddr>,
+    ///The bearer token every API request needs (default: none)
+    pub http_token: Option<String>,
+}
+///The network protocols the bridge listens to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    #[default]
+    Artnet,
+    ///sACN (ANSI E1.31), where Art-Net universe 0 corresponds to sACN universe 1
+    Sacn,
+    Both,
+    ///Only the playback of a show file
+    None,
+}
+
+impl Input {
+    pub const ALL: [Input; 4] = [Input::Artnet, Input::Sacn, Input::Both, Input::None];
+
+    ///The name used on the command line
+    pub fn id(&self) -> &'static str {
+        match self {
+            Input::Artnet => "artnet",
+            Input::Sacn => "sacn",
+            Input::Both => "both",
+            Input::None => "none",
+        }
+    }
+
+    pub fn artnet(&self) -> bool {
+        matches!(self, Input::Artnet | Input::Both)
+    }
+
+    pub fn sacn(&self) -> bool {
+        matches!(self, Input::Sacn | Input::Both)
+    }
+}
+
+impl std::str::FromStr for Input {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "artnet" | "art-net" => Ok(Input::Artnet),
+            "sacn" | "e1.31" => Ok(Input::Sacn),
+            "both" => Ok(Input::Both),
+            "none" => Ok(Input::None),
+            _ => Err(format!("Unknown input \"{s}\"").into()),
+        }
+    }
+}
+
+impl std::fmt::Display for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Input::Artnet => write!(f, "Art-Net"),
+            Input::Sacn => write!(f, "sACN"),
+            Input::Both => write!(f, "Art-Net + sACN"),
+            Input::None => write!(f, "Off"),
+        }
+    }
+}
+
+///How the frames of multiple senders on the same universe get combined
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMode {
+    ///Highest Takes Precedence
+    #[default]
+    Htp,
+    ///Latest Takes Precedence
+    Ltp,
+}
+
+impl MergeMode {
+    pub const ALL: [MergeMode; 2] = [MergeMode::Htp, MergeMode::Ltp];
+}
+
+impl std::str::FromStr for MergeMode {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "htp" => Ok(MergeMode::Htp),
+            "ltp" => Ok(MergeMode::Ltp),
+            _ => Err(format!("Unknown merge mode \"{s}\"").into()),
+        }
+    }
+}
+
+impl std::fmt::Display for MergeMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeMode::Htp => write!(f, "HTP"),
+            MergeMode::Ltp => write!(f, "LTP"),
+        }
+    }
+}
+
+///What the output does once no valid frames arrive anymore
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum LossPolicy {
+    ///Stop refreshing the output and leave it to the fixtures
+    #[default]
+    Off,
+    ///Hold the last values forever
+    Hold,
+    ///Hold the last values for the given time, then blackout
+    Blackout(Duration),
+    ///Fade the last values to black over the given time
+    Fade(Duration),
+    ///Switch to a failsafe scene loaded from the given file
+    Scene(PathBuf),
+}
+
+impl LossPolicy {
+    ///`true` if the output has to be refreshed continuously
+    pub fn refreshes_output(&self) -> bool {
+        *self != LossPolicy::Off
+    }
+}
+
+impl std::str::FromStr for LossPolicy {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (policy, parameter) = match s.split_once(':') {
+            Some((policy, parameter)) => (policy, Some(parameter)),
+            None => (s, None),
+        };
+        let seconds = || -> Result<Duration, Self::Err> {
+            match parameter.map(str::parse::<f32>) {
+                Some(Ok(seconds)) if seconds >= 0.0 && seconds.is_finite() => Ok(Duration::from_secs_f32(seconds)),
+                _ => Err(format!("Invalid loss policy \"{s}\" (expected {policy}:<SECONDS>)").into()),
+            }
+        };
+        match (policy.to_lowercase().as_str(), parameter) {
+            ("off", None) => Ok(LossPolicy::Off),
+            ("hold", None) => Ok(LossPolicy::Hold),
+            ("blackout", _) => Ok(LossPolicy::Blackout(seconds()?)),
+            ("fade", _) => Ok(LossPolicy::Fade(seconds()?)),
+            ("scene", Some(path)) if !path.is_empty() => Ok(LossPolicy::Scene(path.into())),
+            _ => Err(format!("Unknown loss policy \"{s}\"").into()),
+        }
+    }
+}
+
+impl std::fmt::Display for LossPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LossPolicy::Off => write!(f, "off"),
+            LossPolicy::Hold => write!(f, "hold"),
+            LossPolicy::Blackout(time) => write!(f, "blackout:{}", time.as_secs_f32()),
+            LossPolicy::Fade(time) => write!(f, "fade:{}", time.as_secs_f32()),
+            LossPolicy::Scene(path) => write!(f, "scene:{}", path.display()),
+        }
+    }
+}
+
+///Playback of a recorded show file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Playback {
+    pub path: PathBuf,
+    ///Start over at the end of the show (default: false)
+    pub looping: bool,
+    ///Where playback starts in the show (default: 0)
+    pub offset: Duration,
+    ///The playback speed factor (default: 1)
+    pub speed: f32,
+    ///Daily time windows in which the show plays (default: always)
+    pub schedule: Vec<ScheduleWindow>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            looping: false,
+            offset: Duration::ZERO,
+            speed: 1.0,
+            schedule: Vec::new(),
+        }
+    }
+}
+
+///A daily time window in local time, windows ending before they start span midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl ScheduleWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        match self.start <= self.end {
+            true => self.start <= time && time < self.end,
+            false => self.start <= time || time < self.end,
+        }
+    }
+}
+
+impl std::str::FromStr for ScheduleWindow {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once('-').ok_or_else(|| format!("Invalid schedule \"{s}\" (expected HH:MM-HH:MM)"))?;
+        let time = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| format!("Invalid time \"{}\" (expected HH:MM)", time.trim()));
+        Ok(Self {
+            start: time(start)?,
+            end: time(end)?,
+        })
+    }
+}
+
+impl std::fmt::Display for ScheduleWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn schedule_windows_contain_their_start_but_not_their_end() {
+        let window = "08:00-17:30".parse::<ScheduleWindow>().unwrap();
+        assert!(window.contains(time("08:00")));
+        assert!(window.contains(time("12:00")));
+        assert!(!window.contains(time("17:30")));
+        assert!(!window.contains(time("07:59")));
+        assert!(!window.contains(time("23:00")));
+    }
+
+    #[test]
+    fn schedule_windows_span_midnight() {
+        let window = "22:00-02:00".parse::<ScheduleWindow>().unwrap();
+        assert!(window.contains(time("22:00")));
+        assert!(window.contains(time("23:59")));
+        assert!(window.contains(time("00:00")));
+        assert!(window.contains(time("01:59")));
+        assert!(!window.contains(time("02:00")));
+        assert!(!window.contains(time("12:00")));
+        assert!(!window.contains(time("21:59")));
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        assert!("08:00".parse::<ScheduleWindow>().is_err());
+        assert!("08:00-25:00".parse::<ScheduleWindow>().is_err());
+    }
+
+    #[test]
+    fn rejects_options_not_supported_by_a_command() {
+        let given = vec!["-i".to_string(), "--record".to_string()];
+        assert!(reject_options(&given, &["-o"], "play").is_ok());
+        assert_eq!(reject_options(&given, &["--record"], "play"), Err("\"--record\" can't be used with play".to_string()));
+    }
+}
@@ -0,0 +1,165 @@
+//! The TOML config file, its values use the syntax of the command line options
+use std::{fmt::{Display, Formatter}, io, path::{Path, PathBuf}, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{self, Arguments, Route};
+
+const FILE_NAME: &str = "config.toml";
+
+///The settings as they are stored, also used by the HTTP API
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    input: Option<String>,
+    controller: Option<String>,
+    interface: Option<String>,
+    bind_ip: Option<String>,
+    port: Option<u16>,
+    merge: Option<String>,
+    name: Option<String>,
+    short_name: Option<String>,
+    ///In milliseconds
+    break_time: Option<u64>,
+    loss: Option<String>,
+    ///The patch rules, one per line
+    patch: Option<String>,
+    output: Option<String>,
+    ///The port of the OSC controls
+    osc: Option<u16>,
+    ///The address of the HTTP API
+    http: Option<String>,
+    ///The bearer token of the HTTP API
+    http_token: Option<String>,
+    routes: Vec<ConfigRoute>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigRoute {
+    universe: u16,
+    ///The device name, optionally prefixed with the kind of output
+    device: String,
+}
+
+///The config file of the current user, e.g. `~/.config/artnet_to_opendmx/config.toml`
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("artnet_to_opendmx").join(FILE_NAME))
+}
+
+///Loads the config file at `path`, `None` if it doesn't exist
+pub fn load(path: &Path) -> Result<Option<Arguments>, ConfigError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(ConfigError::Io(path.to_path_buf(), error)),
+    };
+    let config = toml::from_str::<Config>(&text).map_err(|error| ConfigError::Parse(path.to_path_buf(), error))?;
+    Arguments::try_from(config).map(Some).map_err(|error| ConfigError::Invalid(path.to_path_buf(), error.to_string()))
+}
+
+impl TryFrom<Config> for Arguments {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(config: Config) -> Result<Self, Self::Error> {
+        let mut arguments = Arguments::default();
+        for route in config.routes {
+            artnet_protocol::PortAddress::try_from(route.universe)?;
+            arguments.routes.push(Route::new(route.universe, &route.device));
+        }
+        cli::check_routes(&arguments.routes)?;
+        let options = &mut arguments.options;
+        if let Some(input) = config.input {
+            options.input = input.parse()?;
+        }
+        if let Some(controller) = config.controller {
+            options.controller = Some(controller.parse()?);
+        }
+        options.interface = config.interface;
+        if let Some(bind_ip) = config.bind_ip {
+            options.bind_ip = Some(bind_ip.parse()?);
+        }
+        options.port = config.port;
+        if let Some(merge) = config.merge {
+            options.merge = merge.parse()?;
+        }
+        options.name = config.name;
+        options.short_name = config.short_name;
+        options.break_time = config.break_time.map(Duration::from_millis);
+        if let Some(loss) = config.loss {
+            options.loss = loss.parse()?;
+        }
+        if let Some(patch) = config.patch {
+            options.patch = Some(patch.parse()?);
+        }
+        if let Some(output) = config.output {
+            options.output = output.parse()?;
+        }
+        options.osc = config.osc;
+        if let Some(http) = config.http {
+            options.http = Some(http.parse()?);
+        }
+        options.http_token = config.http_token.filter(|token| !token.is_empty());
+        Ok(arguments)
+    }
+}
+
+impl From<&Arguments> for Config {
+    fn from(arguments: &Arguments) -> Self {
+        let options = &arguments.options;
+        Config {
+            input: Some(options.input.id().into()),
+            controller: options.controller.as_ref().map(ToString::to_string),
+            interface: options.interface.clone(),
+            bind_ip: options.bind_ip.map(|ip| ip.to_string()),
+            port: options.port,
+            merge: Some(options.merge.to_string().to_lowercase()),
+            name: options.name.clone(),
+            short_name: options.short_name.clone(),
+            break_time: options.break_time.map(|time| time.as_millis() as u64),
+            loss: Some(options.loss.to_string()),
+            patch: options.patch.as_ref().filter(|patch| !patch.is_empty()).map(ToString::to_string),
+            output: Some(options.output.id().into()),
+            osc: options.osc,
+            http: options.http.map(|address| address.to_string()),
+            http_token: options.http_token.clone(),
+            routes: arguments.routes.iter().map(|route| ConfigRoute {
+                universe: route.universe,
+                device: match route.output {
+                    Some(output) => format!("{}:{}", output.id(), route.device_name),
+                    None => route.device_name.clone(),
+                },
+            }).collect(),
+        }
+    }
+}
+
+///Writes `arguments` to the config file at `path`, creating its directory if needed
+pub fn save(path: &Path, arguments: &Arguments) -> Result<(), ConfigError> {
+    let text = toml::to_string(&Config::from(arguments)).map_err(ConfigError::Serialize)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|error| ConfigError::Io(path.to_path_buf(), error))?;
+    }
+    std::fs::write(path, text).map_err(|error| ConfigError::Io(path.to_path_buf(), error))
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, String),
+    Serialize(toml::ser::Error),
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "Couldn't access config file \"{}\": {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "Invalid config file \"{}\": {}", path.display(), e),
+            ConfigError::Invalid(path, e) => write!(f, "Invalid config file \"{}\": {}", path.display(), e),
+            ConfigError::Serialize(e) => write!(f, "Couldn't write config file: {}", e),
+        }
+    }
+}
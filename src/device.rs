@@ -0,0 +1,123 @@
+//! Selecting serial devices by port name or USB ids
+use std::fmt::{Display, Formatter};
+
+use serialport::{SerialPortInfo, SerialPortType, UsbPortInfo};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSelector {
+    Port(String),
+    Usb {
+        vid: u16,
+        pid: u16,
+        serial_number: Option<String>,
+    },
+}
+
+impl DeviceSelector {
+    ///Parses a device name, anything that doesn't start with `usb:` is a port name
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let Some(ids) = name.strip_prefix("usb:") else {
+            return Ok(DeviceSelector::Port(name.into()));
+        };
+        let invalid = || format!("Invalid device \"{name}\" (expected usb:<VID>:<PID>[:serial=<SERIAL>])");
+        let mut parts = ids.split(':');
+        let mut id = || u16::from_str_radix(parts.next().unwrap_or_default(), 16).map_err(|_| invalid());
+        let (vid, pid) = (id()?, id()?);
+        let serial_number = match parts.next() {
+            Some(serial) => Some(serial.strip_prefix("serial=").filter(|serial| !serial.is_empty()).ok_or_else(invalid)?.to_string()),
+            None => None,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(DeviceSelector::Usb { vid, pid, serial_number })
+    }
+
+    ///The selector which finds `port` again, by serial number if it has one
+    pub fn for_port(port: &SerialPortInfo) -> Self {
+        match &port.port_type {
+            SerialPortType::UsbPort(info) => DeviceSelector::Usb {
+                vid: info.vid,
+                pid: info.pid,
+                serial_number: info.serial_number.clone(),
+            },
+            _ => DeviceSelector::Port(port.port_name.clone()),
+        }
+    }
+
+    pub fn matches(&self, port: &SerialPortInfo) -> bool {
+        match (self, &port.port_type) {
+            (DeviceSelector::Port(name), _) => *name == port.port_name,
+            (DeviceSelector::Usb { vid, pid, serial_number }, SerialPortType::UsbPort(info)) => {
+                info.vid == *vid && info.pid == *pid && serial_number.as_ref().is_none_or(|serial| {
+                    info.serial_number.as_ref().is_some_and(|other| other.eq_ignore_ascii_case(serial))
+                })
+            },
+            _ => false,
+        }
+    }
+
+    ///The port name of the first matching device
+    pub fn resolve(&self, ports: &[SerialPortInfo]) -> Option<String> {
+        let mut matching = ports.iter().filter(|port| self.matches(port));
+        let port = matching.next()?;
+        if matching.next().is_some() {
+            log::warn!("Multiple devices match \"{}\", using \"{}\"", self, port.port_name);
+        }
+        Some(port.port_name.clone())
+    }
+}
+
+impl Display for DeviceSelector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceSelector::Port(name) => write!(f, "{}", name),
+            DeviceSelector::Usb { vid, pid, serial_number: Some(serial) } => write!(f, "usb:{:04x}:{:04x}:serial={}", vid, pid, serial),
+            DeviceSelector::Usb { vid, pid, serial_number: None } => write!(f, "usb:{:04x}:{:04x}", vid, pid),
+        }
+    }
+}
+
+///The first two selectors picking the same one of `ports`, with its port name
+pub fn find_clash<'a>(selectors: &[&'a DeviceSelector], ports: &[SerialPortInfo]) -> Option<(&'a DeviceSelector, &'a DeviceSelector, String)> {
+    let resolved = selectors.iter().filter_map(|selector| Some((*selector, selector.resolve(ports)?))).collect::<Vec<_>>();
+    resolved.iter().enumerate().find_map(|(i, (selector, port))| {
+        resolved[..i].iter().find(|(_, other)| other == port).map(|(other, _)| (*other, *selector, port.clone()))
+    })
+}
+
+///Manufacturer and serial number of a USB device, e.g. `FTDI, serial AB12CD`
+pub fn describe(info: &UsbPortInfo) -> String {
+    let mut description = vec![info.manufacturer.clone().unwrap_or(format!("{:04x}:{:04x}", info.vid, info.pid))];
+    if let Some(serial) = &info.serial_number {
+        description.push(format!("serial {}", serial));
+    }
+    description.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usb_port(name: &str, serial_number: &str) -> SerialPortInfo {
+        SerialPortInfo {
+            port_name: name.into(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid: 0x0403,
+                pid: 0x6001,
+                serial_number: Some(serial_number.into()),
+                manufacturer: None,
+                product: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn finds_selectors_of_the_same_port() {
+        let ports = [usb_port("/dev/ttyUSB0", "A"), usb_port("/dev/ttyUSB1", "B")];
+        let selectors = ["/dev/ttyUSB1", "usb:0403:6001", "usb:0403:6001:serial=b"].map(|name| DeviceSelector::parse(name).unwrap());
+        let (first, second, port) = find_clash(&selectors.iter().collect::<Vec<_>>(), &ports).unwrap();
+        assert_eq!((first.to_string(), second.to_string(), port.as_str()), ("/dev/ttyUSB1".into(), "usb:0403:6001:serial=b".into(), "/dev/ttyUSB1"));
+        assert!(find_clash(&selectors[..2].iter().collect::<Vec<_>>(), &ports).is_none());
+    }
+}
@@ -0,0 +1,164 @@
+//! Enttec DMX USB Pro (and compatible widgets like the DMXking ultraDMX)
+use std::{fmt::{Display, Formatter}, io, time::{Duration, Instant}};
+
+use serialport::SerialPort;
+use log::debug;
+
+use crate::output::{DmxOutput, OutputError};
+
+const START_OF_MESSAGE: u8 = 0x7E;
+const END_OF_MESSAGE: u8 = 0xE7;
+const LABEL_GET_PARAMETERS: u8 = 3;
+const LABEL_SET_PARAMETERS: u8 = 4;
+const LABEL_OUTPUT_ONLY_SEND_DMX: u8 = 6;
+const LABEL_GET_SERIAL_NUMBER: u8 = 10;
+
+///The baud rate is ignored by the widgets (they are USB devices), but has to be valid
+const BAUD_RATE: u32 = 57600;
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+///How long detection waits for a reply, Open DMX interfaces never reply
+const REPLY_TIMEOUT: Duration = Duration::from_millis(250);
+///Break and mark after break in units of 10.67µs, the widget defaults
+const BREAK_TIME: u8 = 9;
+const MARK_AFTER_BREAK_TIME: u8 = 1;
+
+///What a widget reported during detection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WidgetInfo {
+    pub serial_number: String,
+    ///Major and minor firmware version
+    pub firmware: (u8, u8),
+}
+
+impl Display for WidgetInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DMX USB Pro (serial {}, firmware {}.{})", self.serial_number, self.firmware.0, self.firmware.1)
+    }
+}
+
+pub struct EnttecPro {
+    name: String,
+    port: Box<dyn SerialPort>,
+    channels: [u8; 512],
+    sync: bool,
+    connected: bool,
+}
+
+impl EnttecPro {
+    pub fn open(name: &str) -> Result<Self, serialport::Error> {
+        Ok(Self {
+            name: name.into(),
+            port: open_port(name)?,
+            channels: [0; 512],
+            sync: true,
+            connected: true,
+        })
+    }
+}
+
+fn open_port(name: &str) -> Result<Box<dyn SerialPort>, serialport::Error> {
+    serialport::new(name, BAUD_RATE).timeout(WRITE_TIMEOUT).open()
+}
+
+///Frames `data` as `0x7E`, label, data length (`u16` LSB first), data, `0xE7`
+fn send_message(port: &mut dyn SerialPort, label: u8, data: &[u8]) -> io::Result<()> {
+    let mut message = Vec::with_capacity(data.len() + 5);
+    message.extend_from_slice(&[START_OF_MESSAGE, label]);
+    message.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    message.extend_from_slice(data);
+    message.push(END_OF_MESSAGE);
+    port.write_all(&message)
+}
+
+///Reads messages until one with `label` arrives, `None` if none did within [`REPLY_TIMEOUT`]
+fn read_message(port: &mut dyn SerialPort, label: u8) -> Option<Vec<u8>> {
+    let start = Instant::now();
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 64];
+    while start.elapsed() < REPLY_TIMEOUT {
+        match port.read(&mut chunk) {
+            Ok(length) => buffer.extend_from_slice(&chunk[..length]),
+            Err(error) if error.kind() == io::ErrorKind::TimedOut => continue,
+            Err(_) => return None,
+        }
+        while let Some(start) = buffer.iter().position(|byte| *byte == START_OF_MESSAGE) {
+            buffer.drain(..start);
+            if buffer.len() < 4 {
+                break;
+            }
+            let length = u16::from_le_bytes([buffer[2], buffer[3]]) as usize;
+            if buffer.len() < length + 5 {
+                break;
+            }
+            let message = buffer.drain(..length + 5).collect::<Vec<_>>();
+            if message[1] == label && message[length + 4] == END_OF_MESSAGE {
+                return Some(message[4..length + 4].to_vec());
+            }
+        }
+    }
+    None
+}
+
+///Asks the device at `name` for its serial number and parameters. `None` if it isn't a Pro widget.
+///
+///Open DMX interfaces briefly output garbage while being probed.
+pub fn detect(name: &str) -> Option<WidgetInfo> {
+    let mut port = serialport::new(name, BAUD_RATE).timeout(Duration::from_millis(20)).open().ok()?;
+    let _ = port.clear(serialport::ClearBuffer::All);
+    send_message(port.as_mut(), LABEL_GET_SERIAL_NUMBER, &[]).ok()?;
+    let serial = read_message(port.as_mut(), LABEL_GET_SERIAL_NUMBER).filter(|data| data.len() >= 4)?;
+    // The serial number is BCD, least significant byte first
+    let serial_number = format!("{:08X}", u32::from_le_bytes([serial[0], serial[1], serial[2], serial[3]]));
+    send_message(port.as_mut(), LABEL_GET_PARAMETERS, &[0, 0]).ok()?;
+    let firmware = match read_message(port.as_mut(), LABEL_GET_PARAMETERS) {
+        Some(parameters) if parameters.len() >= 2 => (parameters[1], parameters[0]),
+        _ => (0, 0),
+    };
+    Some(WidgetInfo { serial_number, firmware })
+}
+
+impl DmxOutput for EnttecPro {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_channels(&mut self, channels: [u8; 512]) {
+        self.channels = channels;
+    }
+
+    fn update(&mut self) -> Result<(), OutputError> {
+        let mut data = [0; 513]; // start code + 512 channels
+        data[1..].copy_from_slice(&self.channels);
+        if let Err(error) = send_message(self.port.as_mut(), LABEL_OUTPUT_ONLY_SEND_DMX, &data) {
+            self.connected = false;
+            return Err(error.into());
+        }
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn is_sync(&self) -> bool {
+        self.sync
+    }
+
+    fn set_sync(&mut self) {
+        self.sync = true;
+    }
+
+    ///The widget repeats the last frame on its own
+    fn set_async(&mut self) -> Result<(), OutputError> {
+        self.sync = false;
+        self.update()
+    }
+
+    ///Sets the output rate of the widget (1-40 packets per second)
+    fn set_packet_time(&mut self, time: Duration) {
+        let rate = (1000 / time.as_millis().max(1)).clamp(1, 40) as u8;
+        if let Err(error) = send_message(self.port.as_mut(), LABEL_SET_PARAMETERS, &[0, 0, BREAK_TIME, MARK_AFTER_BREAK_TIME, rate]) {
+            debug!("Couldn't set the output rate of \"{}\": {}", self.name, error);
+        }
+    }
+}
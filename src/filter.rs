@@ -0,0 +1,123 @@
+//! The controllers the bridge accepts dmx from
+use std::{fmt::{Display, Formatter}, net::{IpAddr, Ipv4Addr}};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderFilter(Vec<Subnet>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Subnet {
+    ip: Ipv4Addr,
+    prefix: u8,
+}
+
+impl Subnet {
+    fn mask(&self) -> u32 {
+        u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0)
+    }
+
+    fn contains(&self, ip: Ipv4Addr) -> bool {
+        u32::from(ip) & self.mask() == u32::from(self.ip) & self.mask()
+    }
+}
+
+impl SenderFilter {
+    pub fn allows(&self, sender: IpAddr) -> bool {
+        match sender {
+            IpAddr::V4(ip) => self.0.iter().any(|subnet| subnet.contains(ip)),
+            IpAddr::V6(ip) => ip.to_ipv4_mapped().is_some_and(|ip| self.allows(IpAddr::V4(ip))),
+        }
+    }
+
+    ///`true` if every sender is allowed
+    pub fn allows_all(&self) -> bool {
+        self.0.iter().any(|subnet| subnet.prefix == 0)
+    }
+}
+
+impl std::str::FromStr for SenderFilter {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let subnets = s.split(',').map(str::trim).filter(|entry| !entry.is_empty()).map(|entry| {
+            let invalid = || format!("Invalid controller \"{entry}\" (expected an address like 2.0.0.10 or a range like 2.0.0.0/8)");
+            let (ip, prefix) = match entry.split_once('/') {
+                Some((ip, prefix)) => (ip, Some(prefix.parse::<u8>().ok().filter(|prefix| *prefix <= 32).ok_or_else(invalid)?)),
+                None => (entry, None),
+            };
+            let ip = ip.parse::<Ipv4Addr>().map_err(|_| invalid())?;
+            let prefix = match prefix {
+                Some(prefix) => prefix,
+                None if ip.is_unspecified() => 0,
+                None => 32,
+            };
+            Ok(Subnet { ip, prefix })
+        }).collect::<Result<Vec<_>, String>>()?;
+        if subnets.is_empty() {
+            return Err("No controller given".into());
+        }
+        Ok(SenderFilter(subnets))
+    }
+}
+
+impl Display for SenderFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, subnet) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            match subnet.prefix {
+                32 => write!(f, "{}", subnet.ip)?,
+                0 if subnet.ip.is_unspecified() => write!(f, "{}", subnet.ip)?,
+                prefix => write!(f, "{}/{}", subnet.ip, prefix)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(s: &str) -> SenderFilter {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn allows_addresses_and_ranges() {
+        let filter = filter("2.0.0.10, 10.0.1.0/24");
+        assert!(filter.allows("2.0.0.10".parse().unwrap()));
+        assert!(!filter.allows("2.0.0.11".parse().unwrap()));
+        assert!(filter.allows("10.0.1.0".parse().unwrap()));
+        assert!(filter.allows("10.0.1.255".parse().unwrap()));
+        assert!(!filter.allows("10.0.2.1".parse().unwrap()));
+        assert!(filter.allows("::ffff:10.0.1.7".parse().unwrap()));
+        assert!(!filter.allows("::1".parse().unwrap()));
+        assert!(!filter.allows_all());
+    }
+
+    #[test]
+    fn matches_ranges_by_prefix_only() {
+        let filter = filter("10.0.1.77/24");
+        assert!(filter.allows("10.0.1.1".parse().unwrap()));
+        assert_eq!(filter.to_string(), "10.0.1.77/24");
+    }
+
+    #[test]
+    fn unspecified_address_allows_everyone() {
+        for s in ["0.0.0.0", "0.0.0.0/0", "2.0.0.10,1.2.3.4/0"] {
+            let filter = filter(s);
+            assert!(filter.allows_all());
+            assert!(filter.allows("192.168.1.1".parse().unwrap()));
+        }
+        assert_eq!(filter("0.0.0.0").to_string(), "0.0.0.0");
+        assert_eq!(filter("2.0.0.10,2.0.0.0/8").to_string(), "2.0.0.10,2.0.0.0/8");
+    }
+
+    #[test]
+    fn rejects_invalid_filters() {
+        for s in ["", " , ", "2.0.0", "2.0.0.0/33", "2.0.0.0/", "host", "::1"] {
+            assert!(s.parse::<SenderFilter>().is_err(), "{s}");
+        }
+    }
+}
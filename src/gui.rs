@@ -2,8 +2,18 @@ use std::net::{SocketAddr, Ipv4Addr};
 use std::sync::mpsc::TryRecvError;
 use std::time::Instant;
 
-use crate::cli::Arguments;
-use crate::runner::{self, RunnerUpdateReciever};
+use crate::api::{ApiCommand, ApiRequest, ApiServer};
+use crate::cli::{self, Arguments, Input, LossPolicy, MergeMode, Playback, Route};
+use crate::config;
+use crate::filter::SenderFilter;
+use crate::device::{self, DeviceSelector};
+use crate::network::{self, NetworkInterface};
+use crate::output::OutputKind;
+use crate::patch::Patch;
+use crate::playback::{PlaybackCommand, PlaybackState, PlaybackStatus};
+use crate::runner::{self, RouteUpdate, RunnerCommand, RunnerHandle, RunnerUpdate};
+use crate::stats::Statistics;
+use crate::supervisor::DeviceState;
 
 use crate::CARGO_PKG_VERSION;
 
@@ -14,7 +24,14 @@ use serialport::{available_ports, SerialPortType};
 use log::{info, error};
 
 const WINDOW_SIZE: egui::Vec2 = egui::Vec2::new(350.0, 200.0);
-const SETTINGS_SIZE: egui::Vec2 = egui::Vec2::new(350.0, 300.0);
+const SETTINGS_SIZE: egui::Vec2 = egui::Vec2::new(350.0, 380.0);
+const SETTINGS_BUTTON_BAR_HEIGHT: f32 = 30.0;
+const MONITOR_SIZE: egui::Vec2 = egui::Vec2::new(500.0, 520.0);
+const MONITOR_COLUMNS: usize = 16;
+///How long a changed channel stays highlighted in the monitor
+const MONITOR_HIGHLIGHT: std::time::Duration = std::time::Duration::from_secs(1);
+const MAX_DISPLAYED_ROUTES: usize = 2;
+const MAX_DISPLAYED_SENDERS: usize = 3;
 
 pub fn run_app(argument_option: Option<Arguments>) -> Result<(), Box<dyn std::error::Error>> {
 
@@ -28,7 +45,7 @@ pub fn run_app(argument_option: Option<Arguments>) -> Result<(), Box<dyn std::er
             .with_transparent(true),
         ..Default::default()
     };
-    eframe::run_native("artnet to opendmx", native_options, Box::new(|_| Box::new(App::new(argument_option))))?;
+    eframe::run_native("artnet to opendmx", native_options, Box::new(|cc| Box::new(App::new(argument_option, cc.egui_ctx.clone()))))?;
     Ok(())
 }
 
@@ -52,38 +69,85 @@ pub(crate) fn load_icon() -> egui::IconData {
 
 struct App {
     available_ports: Vec<serialport::SerialPortInfo>,
-    runner: Option<RunnerUpdateReciever>,
+    network_interfaces: Vec<NetworkInterface>,
+    runner: Option<RunnerHandle>,
     leds: Leds,
+    route_states: Vec<RouteUpdate>,
+    synchronous: bool,
+    recording: bool,
+    playback: Option<PlaybackStatus>,
+    ///All outputs are forced to black, kept over restarts
+    blackout: bool,
+    master: f32,
+    api: Option<ApiServer>,
     last_packet_instant: Option<std::time::Instant>,
     last_packet: Option<(std::time::Duration, SocketAddr)>,
+    statistics: Statistics,
     current_settings: Option<Arguments>,
     temp_config: Option<TempConfig>,
     settings_window_open: bool,
+    statistics_open: bool,
+    monitor_open: bool,
+    monitor: ChannelMonitor,
     manufacturer_filter: bool,
     gui_error_message: String,
-    runner_waiting_for_restart: Option<std::time::Instant>,
 
 }
 
 impl App {
-    fn new(argument_option: Option<Arguments>) -> Self {
+    fn new(argument_option: Option<Arguments>, ctx: egui::Context) -> Self {
+        let start = argument_option.is_some();
         let mut app = App {
             available_ports: available_ports().unwrap(),
+            network_interfaces: network::list(),
             runner: None,
             leds: Leds::default(),
+            route_states: Vec::new(),
+            synchronous: false,
+            recording: false,
+            playback: None,
+            blackout: false,
+            master: 1.0,
+            api: None,
             last_packet_instant: None,
             last_packet: None,
+            statistics: Statistics::default(),
             current_settings: argument_option,
             temp_config: None,
             settings_window_open: false,
+            statistics_open: false,
+            monitor_open: false,
+            monitor: ChannelMonitor::default(),
             manufacturer_filter: true,
             gui_error_message: String::new(),
-            runner_waiting_for_restart: None,
         };
 
-        if app.current_settings.is_some() {
+        if start {
             app.start_runner();
+        } else if let Some(path) = config::default_path() {
+            // Fill in the settings without starting
+            match config::load(&path) {
+                Ok(arguments) => app.current_settings = arguments.map(|mut arguments| {
+                    arguments.options.config = Some(path);
+                    arguments
+                }),
+                Err(error) => {
+                    error!("{}", error);
+                    app.gui_error_message = error.to_string();
+                },
+            }
         }
+        // The API keeps its address until the next start
+        if let Some(address) = app.current_settings.as_ref().and_then(|arguments| arguments.options.http) {
+            match ApiServer::start(address, move || ctx.request_repaint()) {
+                Ok(api) => app.api = Some(api),
+                Err(error) => {
+                    error!("{}", error);
+                    app.gui_error_message = error.to_string();
+                },
+            }
+        }
+        app.publish_settings();
         app
     }
 
@@ -95,20 +159,34 @@ impl App {
                 return;
             },
         }) {
-            Ok(runner_update_reciever) => Some(runner_update_reciever),
+            Ok(runner) => Some(runner),
             Err(error) => {
                 self.gui_error_message = format!("Error while starting: {}", error);
                 return;
             },
         };
+        if self.blackout {
+            self.send(RunnerCommand::Blackout(true));
+        }
         self.last_packet_instant = Some(Instant::now());
+        self.publish_update(None);
     }
 
     fn stop_runner(&mut self) {
-        self.runner = None;
+        if let Some(runner) = self.runner.take() {
+            runner.stop();
+            runner.join();
+        }
         self.leds = Leds::default();
+        self.route_states.clear();
+        self.synchronous = false;
+        self.recording = false;
+        self.playback = None;
+        self.master = 1.0;
         self.last_packet_instant = None;
         self.last_packet = None;
+        self.statistics = Statistics::default();
+        self.publish_update(None);
     }
 
     fn restart_runner(&mut self) {
@@ -116,8 +194,83 @@ impl App {
             return;
         }
         self.stop_runner();
-        self.runner_waiting_for_restart = Some(Instant::now());
+        self.start_runner();
+    }
+
+    fn send(&self, command: RunnerCommand) {
+        if let Some(runner) = &self.runner {
+            runner.send(command);
+        }
+    }
 
+    ///Saves the settings and restarts a running bridge with them
+    fn apply_settings(&mut self, new_settings: Arguments) {
+        if let Some(path) = new_settings.options.config.clone().or_else(config::default_path) {
+            match config::save(&path, &new_settings) {
+                Ok(_) => info!("Saved settings to \"{}\"", path.display()),
+                Err(error) => error!("{}", error),
+            }
+        }
+        self.current_settings = Some(new_settings);
+        self.publish_settings();
+        // A stopped bridge gets the new settings on its next start
+        if self.runner.as_ref().is_some_and(RunnerHandle::is_running) {
+            self.restart_runner();
+        }
+    }
+
+    fn publish_settings(&self) {
+        if let Some(api) = &self.api {
+            api.set_arguments(self.current_settings.as_ref());
+        }
+    }
+
+    fn publish_update(&self, update: Option<RunnerUpdate>) {
+        if let Some(api) = &self.api {
+            api.set_update(self.runner.is_some(), update);
+        }
+    }
+
+    ///Applies the commands of the HTTP API like the controls of the window
+    fn handle_api_requests(&mut self) {
+        let Some(api) = &self.api else {
+            return;
+        };
+        let requests = api.requests().collect::<Vec<_>>();
+        for ApiRequest { command, reply } in requests {
+            let was_running = self.runner.is_some();
+            let stop = matches!(command, ApiCommand::Stop);
+            self.gui_error_message.clear();
+            let start = match command {
+                ApiCommand::Start => !was_running,
+                ApiCommand::Stop => {
+                    self.stop_runner();
+                    false
+                },
+                ApiCommand::Restart => {
+                    self.stop_runner();
+                    true
+                },
+                ApiCommand::Blackout(blackout) => {
+                    self.blackout = blackout;
+                    self.send(RunnerCommand::Blackout(blackout));
+                    false
+                },
+                ApiCommand::Configure(arguments) => {
+                    // Open settings would overwrite the new ones on apply
+                    self.temp_config = None;
+                    self.apply_settings(*arguments);
+                    false
+                },
+            };
+            if start {
+                self.start_runner();
+            }
+            reply.send(match !stop && (start || was_running) && self.runner.is_none() {
+                true => Err(self.gui_error_message.clone()),
+                false => Ok(()),
+            });
+        }
     }
 
     fn status_display(&self, ui: &mut egui::Ui, width: f32) {
@@ -131,15 +284,15 @@ impl App {
         };
 
 
-        let size = egui::vec2(width, width/1.61803398875);
+        let size = egui::vec2(width, width/1.618_034);
         let (_, mut rect) = ui.allocate_space(size);
 
         ui.painter().rect_filled(rect, width*0.02, fg_color);
-        let org_rect = rect.clone();
+        let org_rect = rect;
         rect = rect.shrink(2.0);
         rect.set_top(rect.top() + 12.0);
         rect.set_height(40.0);
-        let name_rect = rect.clone();
+        let name_rect = rect;
         ui.painter().rect_filled(rect, width*0.02, bg_color);
         rect.set_top(rect.bottom() + 2.0);
         rect.set_bottom(org_rect.bottom() - 2.0);
@@ -148,7 +301,7 @@ impl App {
         ui.painter().text(
             org_rect.center_top(),
             egui::Align2::CENTER_TOP,
-            "Device Info",
+            if self.statistics_open { "Statistics" } else { "Device Info" },
             egui::FontId::monospace(13.0),
             bg_color,
         );
@@ -157,43 +310,75 @@ impl App {
                 name_rect.center(),
                 egui::Align2::CENTER_CENTER,
                 match self.current_settings.as_ref() {
-                    Some(args) => format!("{}", match args.options.name.clone() {
-                        Some(name) => name,
-                        None => "artnet2opendmx".into()
-                    }),
+                    Some(args) => args.options.name.clone().unwrap_or("artnet2opendmx".into()),
                     None => "No Config".to_string(),
                 },
                 egui::FontId::monospace(20.0),
                 fg_color,
             );
 
-            if let Some(arguments) = &self.current_settings {
+            if self.statistics_open {
+                self.statistics_display(ui, rect, fg_color);
+            } else if let Some(arguments) = &self.current_settings {
                 ui.painter().text(
                     rect.center_top(),
                     egui::Align2::CENTER_TOP,
-                    format!("Listen: {}@{}", match &arguments.options.controller {
-                        Some(controller) => controller,
-                        None => "BROADCAST",
-                    }, match arguments.options.port {
-                        Some(port) => port,
-                        None => 6454,
+                    format!("Listen: {}{}", match arguments.options.input {
+                        Input::Sacn => "sACN".to_string(),
+                        Input::None => "Off".to_string(),
+                        input => format!("{}@{}{}{}", match (&arguments.options.interface, arguments.options.bind_ip) {
+                            (Some(interface), _) => interface.clone(),
+                            (None, Some(ip)) => ip.to_string(),
+                            (None, None) => "ALL".into(),
+                        }, arguments.options.port.unwrap_or(6454), match &arguments.options.controller {
+                            Some(controller) => format!(" ⏴ {}", controller),
+                            None => "".into(),
+                        }, if input.sacn() { " + sACN" } else { "" }),
+                    }, match (self.synchronous, self.recording) {
+                        (_, true) => " ● REC",
+                        (true, false) => " (Sync)",
+                        (false, false) => "",
                     }),
                     egui::FontId::monospace(10.0),
                     fg_color,
                 );
+                for (i, route) in arguments.routes.iter().enumerate() {
+                    rect.set_top(rect.top() + 10.0);
+                    if i == MAX_DISPLAYED_ROUTES && arguments.routes.len() > MAX_DISPLAYED_ROUTES + 1 {
+                        ui.painter().text(
+                            rect.center_top(),
+                            egui::Align2::CENTER_TOP,
+                            format!("... +{} more routes", arguments.routes.len() - MAX_DISPLAYED_ROUTES),
+                            egui::FontId::monospace(10.0),
+                            fg_color,
+                        );
+                        break;
+                    }
+                    let state = self.route_states.get(i);
+                    let device = match state.map(|state| state.device) {
+                        Some(DeviceState::Waiting) => " (Waiting)",
+                        Some(DeviceState::Lost) => " ⚠ Lost",
+                        Some(DeviceState::Connected) | None => "",
+                    };
+                    let signal_lost = state.is_some_and(|state| state.signal_lost);
+                    ui.painter().text(
+                        rect.center_top(),
+                        egui::Align2::CENTER_TOP,
+                        format!("Universe: {} ⏵ COM: {}{}{}", route.universe, route.device_name, if signal_lost { " (No Signal)" } else { "" }, device),
+                        egui::FontId::monospace(10.0),
+                        fg_color,
+                    );
+                }
                 rect.set_top(rect.top() + 10.0);
                 ui.painter().text(
                     rect.center_top(),
                     egui::Align2::CENTER_TOP,
-                    format!("Universe: {} ⏵ COM: {}", arguments.universe, arguments.device_name),
-                    egui::FontId::monospace(10.0),
-                    fg_color,
-                );
-                rect.set_top(rect.top() + 10.0);
-                ui.painter().text(
-                    rect.center_top(),
-                    egui::Align2::CENTER_TOP,
-                    format!("Remembering: {}", if arguments.options.remember { "True" } else { "False"}),
+                    match &self.playback {
+                        _ if self.blackout => "Output: BLACKOUT".to_string(),
+                        _ if self.master < 1.0 => format!("Output: Master {:.0}%", self.master * 100.0),
+                        Some(playback) => format!("Playback: {} / {} ({})", format_time(playback.position), format_time(playback.length), playback.state),
+                        None => format!("On Signal Loss: {}", arguments.options.loss),
+                    },
                     egui::FontId::monospace(10.0),
                     fg_color,
                 );
@@ -203,7 +388,10 @@ impl App {
                     ui.painter().text(
                         rect.center_bottom(),
                         egui::Align2::CENTER_BOTTOM,
-                        format!("Sender: {}", sender),
+                        match self.route_states.iter().map(|route| route.sources).max() {
+                            Some(sources) if sources > 1 => format!("Merging: {} sources ({})", sources, arguments.options.merge),
+                            _ => format!("Sender: {}", sender),
+                        },
                         egui::FontId::monospace(10.0),
                         fg_color,
                     );
@@ -230,6 +418,123 @@ impl App {
     }
 }
 
+impl App {
+    fn statistics_display(&self, ui: &egui::Ui, mut rect: egui::Rect, fg_color: egui::Color32) {
+        let statistics = &self.statistics;
+        let mut lines = vec![
+            format!("In: {:.1} pkt/s  Out: {:.1} fps", statistics.packets_per_second, statistics.frames_per_second),
+            format!("Packets: {} (Other Universes: {})", statistics.packets, statistics.other_universe_packets),
+            format!("Serial Errors: {}  Reconnects: {}", statistics.serial_failures, statistics.reconnects),
+        ];
+        if statistics.rejected_packets > 0 {
+            lines.push(format!("Rejected: {} packets from {} senders", statistics.rejected_packets, statistics.senders.iter().filter(|sender| sender.rejected).count()));
+        }
+        for (i, sender) in statistics.senders.iter().enumerate() {
+            if i == MAX_DISPLAYED_SENDERS - 1 && statistics.senders.len() > MAX_DISPLAYED_SENDERS {
+                lines.push(format!("... +{} more senders", statistics.senders.len() - i));
+                break;
+            }
+            lines.push(format!("{} ({}): {:.1}/s{}", sender.ip, sender.protocol, sender.packets_per_second, if sender.rejected { " ✖" } else { "" }));
+        }
+        for line in lines {
+            ui.painter().text(
+                rect.center_top(),
+                egui::Align2::CENTER_TOP,
+                line,
+                egui::FontId::monospace(10.0),
+                fg_color,
+            );
+            rect.set_top(rect.top() + 10.0);
+        }
+    }
+}
+
+impl App {
+    ///The 512 channels of one route as they are sent to its device
+    fn monitor_display(&mut self, ui: &mut egui::Ui) {
+        let routes = self.current_settings.as_ref().map(|arguments| arguments.routes.clone()).unwrap_or_default();
+        if self.monitor.route >= routes.len() {
+            self.monitor.route = 0;
+        }
+        let route_name = |route: &Route| format!("Universe {} ⏵ {}", route.universe, route.device_name);
+
+        ui.add_space(4.0);
+        // Shows the hovered channel
+        let hovered = ui.horizontal(|ui| {
+            ui.add_space(4.0);
+            let selected = routes.get(self.monitor.route).map(route_name).unwrap_or("No Route".into());
+            egui::ComboBox::from_id_source("monitor_route_selection").selected_text(selected).width(180.0).show_ui(ui, |ui| {
+                for (i, route) in routes.iter().enumerate() {
+                    ui.selectable_value(&mut self.monitor.route, i, route_name(route));
+                }
+            });
+            for format in ValueFormat::ALL {
+                ui.selectable_value(&mut self.monitor.format, format, format.to_string());
+            }
+            ui.label("")
+        }).inner;
+        ui.add_space(4.0);
+
+        let bg_color = egui::Color32::from_rgb(0, 0, 0);
+        let fg_color = egui::Color32::from_rgb(167, 219, 235);
+        let highlight_color = egui::Color32::from_rgb(255, 204, 102);
+        let state = self.route_states.get(self.monitor.route);
+        let fg_color = if state.is_none() {
+            fg_color.gamma_multiply(0.5)
+        } else {
+            fg_color
+        };
+        let channels = state.map(|state| state.channels).unwrap_or([0; 512]);
+        let changes = self.monitor.changes.get(self.monitor.route);
+
+        let (rect, response) = ui.allocate_exact_size(ui.available_size() - egui::vec2(4.0, 4.0), egui::Sense::hover());
+        ui.painter().rect_filled(rect, 4.0, bg_color);
+        let label_width = 32.0;
+        let rows = 512 / MONITOR_COLUMNS;
+        let cell_size = egui::vec2((rect.width() - label_width) / MONITOR_COLUMNS as f32, rect.height() / rows as f32);
+        for row in 0..rows {
+            let top = rect.top() + row as f32 * cell_size.y;
+            ui.painter().text(
+                egui::pos2(rect.left() + label_width - 6.0, top + cell_size.y / 2.0),
+                egui::Align2::RIGHT_CENTER,
+                row * MONITOR_COLUMNS + 1,
+                egui::FontId::monospace(9.0),
+                fg_color.gamma_multiply(0.5),
+            );
+            for column in 0..MONITOR_COLUMNS {
+                let index = row * MONITOR_COLUMNS + column;
+                let cell = egui::Rect::from_min_size(egui::pos2(rect.left() + label_width + column as f32 * cell_size.x, top), cell_size);
+                // Fades out after a change
+                let changed = changes.and_then(|changes| changes[index]).map(|instant| instant.elapsed()).filter(|elapsed| *elapsed < MONITOR_HIGHLIGHT);
+                if let Some(elapsed) = changed {
+                    let fade = 1.0 - elapsed.as_secs_f32() / MONITOR_HIGHLIGHT.as_secs_f32();
+                    ui.painter().rect_filled(cell.shrink(1.0), 2.0, highlight_color.gamma_multiply(fade * 0.6));
+                }
+                ui.painter().text(
+                    cell.center(),
+                    egui::Align2::CENTER_CENTER,
+                    self.monitor.format.format(channels[index]),
+                    egui::FontId::monospace(10.0),
+                    if channels[index] == 0 { fg_color.gamma_multiply(0.4) } else { fg_color },
+                );
+                if response.hover_pos().is_some_and(|position| cell.contains(position)) {
+                    ui.painter().rect_stroke(cell, 2.0, egui::Stroke::new(1.0, fg_color));
+                    ui.painter().text(
+                        hovered.rect.left_center(),
+                        egui::Align2::LEFT_CENTER,
+                        format!("Ch {}: {} / {}% / {:02X}", index + 1, channels[index], percent(channels[index]), channels[index]),
+                        egui::FontId::monospace(10.0),
+                        ui.style().visuals.text_color(),
+                    );
+                }
+            }
+        }
+        if self.monitor.changes.iter().flatten().flatten().any(|instant| instant.elapsed() < MONITOR_HIGHLIGHT) {
+            ui.ctx().request_repaint();
+        }
+    }
+}
+
 impl eframe::App for App {
     fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
         egui::Rgba::TRANSPARENT.to_array() // Make sure we don't paint anything behind the rounded corners
@@ -260,13 +565,19 @@ impl eframe::App for App {
             let mut settings_window = false;
             let title = if self.settings_window_open {
                 "Settings"
+            } else if self.monitor_open {
+                "Channel Monitor"
             } else {
                 "artnet2opendmx"
             };
-            title_bar_ui(ui, title_bar_rect, title, &mut settings_window);
+            let monitor_open = self.monitor_open;
+            title_bar_ui(ui, title_bar_rect, title, &mut settings_window, &mut self.statistics_open, &mut self.monitor_open);
             if settings_window {
                 ui.ctx().send_viewport_cmd(ViewportCommand::InnerSize(SETTINGS_SIZE));
                 self.settings_window_open = true;
+                self.monitor_open = false;
+            } else if self.monitor_open != monitor_open && !self.settings_window_open {
+                ui.ctx().send_viewport_cmd(ViewportCommand::InnerSize(if self.monitor_open { MONITOR_SIZE } else { WINDOW_SIZE }));
             }
 
             // Add the contents:
@@ -279,28 +590,38 @@ impl eframe::App for App {
             let mut ui = ui.child_ui(content_rect, *ui.layout());
 
             //LOGIC
-            if let Some(instant) = &self.runner_waiting_for_restart {
-                if instant.elapsed() > std::time::Duration::from_secs(1) {
-                    self.runner_waiting_for_restart = None;
-                    self.start_runner();
-                }
-            }
+            self.handle_api_requests();
             if let Some(runner) = &self.runner {
-                match runner.try_recv() {
+                match runner.updates().try_recv() {
                     Ok(update) => {
+                        self.publish_update(Some(update.clone()));
                         self.leds.link = update.connected_to_artnet;
-                        self.leds.dmx = update.dmx_recieved.is_some();
-                        self.leds.com = update.connected_to_dmx;
-                        self.leds.act = update.dmx_sent;
+                        self.leds.dmx = update.routes.iter().any(|route| route.dmx_recieved.is_some());
+                        self.leds.com = update.routes.iter().all(|route| route.device == DeviceState::Connected);
+                        self.leds.act = update.routes.iter().any(|route| route.dmx_sent);
 
-                        if let Some(sender) = update.dmx_recieved {
+                        if let Some(sender) = update.routes.iter().find_map(|route| route.dmx_recieved) {
                             self.last_packet = Some((self.last_packet_instant.unwrap().elapsed(), sender));
                             self.last_packet_instant = Some(Instant::now());
                         }
+                        self.monitor.track_changes(&self.route_states, &update.routes);
+                        self.route_states = update.routes;
+                        self.synchronous = update.synchronous;
+                        self.recording = update.recording;
+                        self.playback = update.playback;
+                        self.blackout = update.blackout;
+                        self.master = update.master;
+                        self.statistics = update.statistics;
+                        if let Some(arguments) = update.reconfigured {
+                            info!("Settings changed by the controller");
+                            self.current_settings = Some(arguments);
+                            self.publish_settings();
+                        }
                         ctx.request_repaint();
                     },
-                    Err(TryRecvError::Empty) => {
-                        ctx.request_repaint();
+                    Err(TryRecvError::Empty) if runner.is_running() => {
+                        // The runner only sends updates on changes
+                        ctx.request_repaint_after(runner::UPDATE_INTERVAL);
                     },
                     Err(_) => {
                         self.stop_runner()
@@ -318,65 +639,152 @@ impl eframe::App for App {
 
                 let mut temp_config = self.temp_config.clone().unwrap();
 
-                ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
-                    ui.columns(2, |cols| {
-                        cols[0].with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                            ui.label(egui::RichText::new("Art-Net").heading().strong());
-                            ui.separator();
-
-                            ui.label(egui::RichText::new("Node Name:").underline().strong()).on_hover_text("max. 18 Characters");
-                            ui.add(egui::TextEdit::singleline(&mut temp_config.artnet_name).desired_width(150.0));
-                            ui.add_space(10.0);
-                            ui.label(egui::RichText::new("Controller IP Address:").underline().strong());
-                            ui.checkbox(&mut temp_config.broadcast,"Recieve Broadcast");
-                            ui.add(egui::TextEdit::singleline(&mut temp_config.controller).desired_width(100.0).interactive(!temp_config.broadcast));
-                            ui.add_space(10.0);
-                            ui.label(egui::RichText::new("Port:").underline().strong()).on_hover_text("0-65535");
-                            ui.add(egui::TextEdit::singleline(&mut temp_config.port).desired_width(50.0));
-                            ui.add_space(10.0);
-                            ui.label(egui::RichText::new("Universe:").underline().strong()).on_hover_text("0-32787");
-                            ui.add(egui::TextEdit::singleline(&mut temp_config.universe).desired_width(50.0));
+                egui::ScrollArea::vertical().max_height(ui.available_height() - SETTINGS_BUTTON_BAR_HEIGHT).show(&mut ui, |ui| {
+                    ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                        ui.columns(2, |cols| {
+                            cols[0].with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                                ui.label(egui::RichText::new("Network").heading().strong());
+                                ui.separator();
 
-                        });
-                        cols[1].with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                            ui.label(egui::RichText::new("Open-DMX").heading().strong());
-                            ui.separator();
+                                ui.label(egui::RichText::new("Protocol:").underline().strong()).on_hover_text("Art-Net universe 0 is sACN universe 1");
+                                egui::ComboBox::from_id_source("input_selection").selected_text(temp_config.input.to_string()).show_ui(ui, |ui| {
+                                    for input in Input::ALL {
+                                        ui.selectable_value(&mut temp_config.input, input, input.to_string());
+                                    }
+                                });
+                                ui.add_space(10.0);
 
-                            ui.label(egui::RichText::new("Serial-Port:").underline().strong());
-                            ui.horizontal(|ui| {
-                                ui.style_mut().spacing.item_spacing.x = 0.0;
-                                if ui.add(egui::Button::new(egui::RichText::new("🔄"))).clicked() {
-                                    info!("Refreshing Serial Port List...");
-                                    self.available_ports = serialport::available_ports().unwrap_or_default();
-                                }
-                                egui::ComboBox::from_id_source("serial_port_selection").selected_text(temp_config.serial_name.clone()).width(ui.available_width()-ui.available_height()).show_ui(ui, |ui| {
-                                    for port in self.available_ports.iter() {
-                                        let manufacturer = match &port.port_type {
-                                            SerialPortType::UsbPort(info) => info.manufacturer.clone().unwrap_or("".into()),
-                                            _ => "".into(),
-                                        };
-                                        if self.manufacturer_filter && !manufacturer.to_lowercase().contains("ftdi") {
-                                            continue;
-                                        }
-                                        let port = format!("{}", port.port_name);
-                                        // let port = port.port_name.clone();
-                                        ui.selectable_value(&mut temp_config.serial_name, port.clone(), port);
+                                ui.label(egui::RichText::new("Node Name:").underline().strong()).on_hover_text("max. 18 Characters");
+                                ui.add(egui::TextEdit::singleline(&mut temp_config.artnet_name).desired_width(150.0));
+                                ui.add_space(10.0);
+                                ui.label(egui::RichText::new("Short Name:").underline().strong()).on_hover_text("max. 17 Characters");
+                                ui.add(egui::TextEdit::singleline(&mut temp_config.short_name).desired_width(150.0));
+                                ui.add_space(10.0);
+                                ui.horizontal(|ui| {
+                                    ui.label(egui::RichText::new("Interface:").underline().strong()).on_hover_text("The network interface to listen on, reported to the controllers");
+                                    if ui.add(egui::Button::new(egui::RichText::new("🔄"))).clicked() {
+                                        info!("Refreshing Network Interface List...");
+                                        self.network_interfaces = network::list();
                                     }
                                 });
+                                egui::ComboBox::from_id_source("interface_selection").selected_text(temp_config.interface.clone().unwrap_or("All".into())).width(150.0).show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut temp_config.interface, None, "All");
+                                    for interface in self.network_interfaces.iter() {
+                                        ui.selectable_value(&mut temp_config.interface, Some(interface.name.clone()), interface.to_string());
+                                    }
+                                });
+                                ui.add_space(10.0);
+                                ui.label(egui::RichText::new("Controller IP Addresses:").underline().strong()).on_hover_text("Addresses or ranges, separated by commas (e.g. 2.0.0.10, 2.0.1.0/24)");
+                                ui.checkbox(&mut temp_config.broadcast,"All Controllers");
+                                ui.add(egui::TextEdit::singleline(&mut temp_config.controller).desired_width(150.0).interactive(!temp_config.broadcast));
+                                ui.add_space(10.0);
+                                ui.label(egui::RichText::new("Port:").underline().strong()).on_hover_text("0-65535");
+                                ui.add(egui::TextEdit::singleline(&mut temp_config.port).desired_width(50.0));
+                                ui.add_space(10.0);
+                                ui.label(egui::RichText::new("Merge Mode:").underline().strong()).on_hover_text("How multiple senders on one universe are combined");
+                                egui::ComboBox::from_id_source("merge_selection").selected_text(temp_config.merge.to_string()).show_ui(ui, |ui| {
+                                    for merge in MergeMode::ALL {
+                                        ui.selectable_value(&mut temp_config.merge, merge, merge.to_string());
+                                    }
+                                });
+
                             });
-                            ui.checkbox(&mut self.manufacturer_filter, "Only show FTDI Devices");
-                            ui.add_space(10.0);
-                            ui.label(egui::RichText::new("Output:").underline().strong());
-                            ui.checkbox(&mut temp_config.custom_break_time, "Custom Break Time");
-                            if temp_config.custom_break_time {
+                            cols[1].with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                                ui.label(egui::RichText::new("Open-DMX").heading().strong());
+                                ui.separator();
+
                                 ui.horizontal(|ui| {
-                                    ui.add(egui::TextEdit::singleline(&mut temp_config.break_time).desired_width(20.0));
-                                    ui.label(egui::RichText::new("ms"));
+                                    ui.label(egui::RichText::new("Routes:").underline().strong()).on_hover_text("Universe (0-32767) ⏵ Serial-Port");
+                                    ui.style_mut().spacing.item_spacing.x = 0.0;
+                                    if ui.add(egui::Button::new(egui::RichText::new("🔄"))).clicked() {
+                                        info!("Refreshing Serial Port List...");
+                                        self.available_ports = serialport::available_ports().unwrap_or_default();
+                                    }
+                                    if ui.add(egui::Button::new(egui::RichText::new("➕"))).on_hover_text("Add route").clicked() {
+                                        temp_config.routes.push(TempRoute::default());
+                                    }
                                 });
-                            }
-                            ui.checkbox(&mut temp_config.remember,"Remember last values");
+                                let mut removed_route = None;
+                                egui::ScrollArea::vertical().max_height(60.0).show(ui, |ui| {
+                                    for (i, route) in temp_config.routes.iter_mut().enumerate() {
+                                        ui.horizontal(|ui| {
+                                            ui.style_mut().spacing.item_spacing.x = 2.0;
+                                            ui.add(egui::TextEdit::singleline(&mut route.universe).desired_width(30.0));
+                                            if !route.output.unwrap_or(temp_config.output).is_device() {
+                                                ui.add(egui::TextEdit::singleline(&mut route.serial_name).desired_width(ui.available_width()-2.0*ui.available_height()).hint_text("Name"));
+                                            } else {
+                                                egui::ComboBox::from_id_source(("serial_port_selection", i)).selected_text(route.serial_name.clone()).width(ui.available_width()-2.0*ui.available_height()).show_ui(ui, |ui| {
+                                                    for port in self.available_ports.iter() {
+                                                        let (manufacturer, label, name) = match &port.port_type {
+                                                            SerialPortType::UsbPort(info) => (
+                                                                info.manufacturer.clone().unwrap_or("".into()),
+                                                                format!("{} ({})", port.port_name, device::describe(info)),
+                                                                // Devices with a serial number are found again on any port
+                                                                match info.serial_number {
+                                                                    Some(_) => DeviceSelector::for_port(port).to_string(),
+                                                                    None => port.port_name.clone(),
+                                                                },
+                                                            ),
+                                                            _ => ("".into(), port.port_name.clone(), port.port_name.clone()),
+                                                        };
+                                                        if self.manufacturer_filter && !manufacturer.to_lowercase().contains("ftdi") {
+                                                            continue;
+                                                        }
+                                                        ui.selectable_value(&mut route.serial_name, name, label);
+                                                    }
+                                                });
+                                            }
+                                            if ui.add_enabled(i > 0, egui::Button::new(egui::RichText::new("🗑"))).on_hover_text("Remove route").clicked() {
+                                                removed_route = Some(i);
+                                            }
+                                        });
+                                    }
+                                });
+                                if let Some(i) = removed_route {
+                                    temp_config.routes.remove(i);
+                                }
+                                ui.checkbox(&mut self.manufacturer_filter, "Only show FTDI Devices");
+                                ui.add_space(10.0);
+                                ui.label(egui::RichText::new("Output:").underline().strong()).on_hover_text("Null, File and Pseudo-terminal outputs need no device");
+                                egui::ComboBox::from_id_source("output_selection").selected_text(temp_config.output.to_string()).show_ui(ui, |ui| {
+                                    for output in OutputKind::ALL {
+                                        ui.selectable_value(&mut temp_config.output, output, output.to_string());
+                                    }
+                                });
+                                ui.checkbox(&mut temp_config.custom_break_time, "Custom Break Time");
+                                if temp_config.custom_break_time {
+                                    ui.horizontal(|ui| {
+                                        ui.add(egui::TextEdit::singleline(&mut temp_config.break_time).desired_width(20.0));
+                                        ui.label(egui::RichText::new("ms"));
+                                    });
+                                }
+                                ui.add_space(10.0);
+                                ui.label(egui::RichText::new("On Signal Loss:").underline().strong()).on_hover_text("What the output does when no frame arrived for 4s");
+                                egui::ComboBox::from_id_source("loss_selection").selected_text(temp_config.loss_kind.to_string()).show_ui(ui, |ui| {
+                                    for kind in LossKind::ALL {
+                                        ui.selectable_value(&mut temp_config.loss_kind, kind, kind.to_string());
+                                    }
+                                });
+                                match temp_config.loss_kind {
+                                    LossKind::Blackout | LossKind::Fade => {
+                                        ui.horizontal(|ui| {
+                                            ui.add(egui::TextEdit::singleline(&mut temp_config.loss_seconds).desired_width(30.0));
+                                            ui.label(egui::RichText::new("s"));
+                                        });
+                                    },
+                                    LossKind::Scene => {
+                                        ui.add(egui::TextEdit::singleline(&mut temp_config.loss_scene).desired_width(150.0).hint_text("Scene file"));
+                                    },
+                                    LossKind::Off | LossKind::Hold => {},
+                                }
+                            });
+                        });
+                        ui.separator();
+                        ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                            ui.label(egui::RichText::new("Channel Patch:").underline().strong()).on_hover_text("<INPUT> -> <OUTPUTS> per line, unpatched channels pass through");
+                            ui.add(egui::TextEdit::multiline(&mut temp_config.patch).desired_rows(3).desired_width(f32::INFINITY).font(egui::TextStyle::Monospace).hint_text("101-120 -> 1-20"));
                         });
-                    })
+                    });
                 });
                 ui.with_layout(egui::Layout::bottom_up(egui::Align::RIGHT), |ui| {
                     ui.add_space(2.0);
@@ -417,13 +825,17 @@ impl eframe::App for App {
                             ui.ctx().send_viewport_cmd(ViewportCommand::Title(String::from("artnet to opendmx")));
                             self.gui_error_message = "".into();
 
-                            self.current_settings = Some(new_settings.clone());
-                            self.restart_runner();
+                            self.apply_settings(new_settings);
                         }
                     });
                 });
                 return;
             }
+            //MONITOR
+            if self.monitor_open {
+                self.monitor_display(&mut ui);
+                return;
+            }
 
             //UI
             ui.add_space(10.0);
@@ -445,10 +857,39 @@ impl eframe::App for App {
                 cols[1].with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
                     self.status_display(ui, 200.0);
                     ui.add_space(4.0);
-                    if self.runner_waiting_for_restart.is_some() {
-                        ui.add_enabled(false, egui::Button::new("Starting...").min_size(egui::vec2(50.0, 0.0)));
-                    } else if self.runner.is_some() {
-                        if ui.add(egui::Button::new("Stop").min_size(egui::vec2(50.0, 0.0))).clicked() {
+                    if let Some(runner) = &self.runner {
+                        let mut stop = false;
+                        let mut blackout = false;
+                        ui.horizontal(|ui| {
+                            let width = if self.playback.is_some() { 196.0 } else { 136.0 };
+                            ui.add_space((ui.available_width() - width) / 2.0);
+                            stop = ui.add(egui::Button::new("Stop").min_size(egui::vec2(50.0, 0.0))).clicked();
+                            // Follows the runner, OSC and the API change it too
+                            blackout = ui.add(egui::Button::new("BO").selected(self.blackout).min_size(egui::vec2(26.0, 0.0))).on_hover_text("Blackout").clicked();
+                            let record_text = if self.recording { "⏹ Rec" } else { "⏺ Rec" };
+                            if ui.add(egui::Button::new(record_text).min_size(egui::vec2(50.0, 0.0))).on_hover_text("Record the output to a show file").clicked() {
+                                runner.send(match self.recording {
+                                    true => RunnerCommand::StopRecording,
+                                    false => RunnerCommand::StartRecording(recording_file_name().into()),
+                                });
+                            }
+                            if let Some(playback) = &self.playback {
+                                if ui.add(egui::Button::new("⏮").min_size(egui::vec2(26.0, 0.0))).on_hover_text("Restart the show").clicked() {
+                                    runner.send(RunnerCommand::Playback(PlaybackCommand::Restart));
+                                }
+                                if ui.add(egui::Button::new("⏯").min_size(egui::vec2(26.0, 0.0))).on_hover_text("Play or pause the show").clicked() {
+                                    runner.send(RunnerCommand::Playback(match playback.state {
+                                        PlaybackState::Playing => PlaybackCommand::Pause,
+                                        _ => PlaybackCommand::Play,
+                                    }));
+                                }
+                            }
+                        });
+                        if blackout {
+                            self.blackout = !self.blackout;
+                            self.send(RunnerCommand::Blackout(self.blackout));
+                        }
+                        if stop {
                             self.stop_runner();
                         }
                     } else {
@@ -485,6 +926,8 @@ fn title_bar_ui(
     title_bar_rect: eframe::epaint::Rect,
     title: &str,
     settings_open: &mut bool,
+    statistics_open: &mut bool,
+    monitor_open: &mut bool,
 ) {
     use egui::*;
 
@@ -507,6 +950,14 @@ fn title_bar_ui(
             ui.add_space(8.0);
             let settings_response = ui.add(Button::new(RichText::new("⛭").size(12.0)));
             *settings_open = settings_response.clicked();
+            let statistics_response = ui.add(Button::new(RichText::new("📊").size(12.0))).on_hover_text("Statistics");
+            if statistics_response.clicked() {
+                *statistics_open = !*statistics_open;
+            }
+            let monitor_response = ui.add(Button::new(RichText::new("▦").size(12.0))).on_hover_text("Channel Monitor");
+            if monitor_response.clicked() {
+                *monitor_open = !*monitor_open;
+            }
         });
     });
 
@@ -537,6 +988,16 @@ fn close_maximize_minimize(ui: &mut egui::Ui) {
     }
 }
 
+///A new show file name in the working directory
+fn recording_file_name() -> String {
+    let time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    format!("recording_{}.dmxshow", time.as_secs())
+}
+
+fn format_time(time: std::time::Duration) -> String {
+    format!("{:02}:{:02}", time.as_secs() / 60, time.as_secs() % 60)
+}
+
 fn signal_lamp(ui: &mut egui::Ui, size: f32, color: egui::Color32, on: bool) {
     let (_, rect) = ui.allocate_space(egui::vec2(size, size));
     let color = if !on {
@@ -547,6 +1008,63 @@ fn signal_lamp(ui: &mut egui::Ui, size: f32, color: egui::Color32, on: bool) {
     ui.painter().circle_filled(rect.center(), size/2.0, color);
 }
 
+fn percent(value: u8) -> u8 {
+    (value as f32 * 100.0 / 255.0).round() as u8
+}
+
+#[derive(Default)]
+struct ChannelMonitor {
+    ///The shown route
+    route: usize,
+    format: ValueFormat,
+    ///When each channel of each route last changed
+    changes: Vec<[Option<Instant>; 512]>,
+}
+
+impl ChannelMonitor {
+    fn track_changes(&mut self, previous: &[RouteUpdate], routes: &[RouteUpdate]) {
+        self.changes.resize(routes.len(), [None; 512]);
+        let now = Instant::now();
+        for ((route, previous), changes) in routes.iter().zip(previous.iter()).zip(self.changes.iter_mut()) {
+            for ((value, previous), changed) in route.channels.iter().zip(previous.channels.iter()).zip(changes.iter_mut()) {
+                if value != previous {
+                    *changed = Some(now);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ValueFormat {
+    #[default]
+    Decimal,
+    Percent,
+    Hex,
+}
+
+impl ValueFormat {
+    const ALL: [ValueFormat; 3] = [ValueFormat::Decimal, ValueFormat::Percent, ValueFormat::Hex];
+
+    fn format(&self, value: u8) -> String {
+        match self {
+            ValueFormat::Decimal => value.to_string(),
+            ValueFormat::Percent => percent(value).to_string(),
+            ValueFormat::Hex => format!("{:02X}", value),
+        }
+    }
+}
+
+impl std::fmt::Display for ValueFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueFormat::Decimal => write!(f, "0-255"),
+            ValueFormat::Percent => write!(f, "%"),
+            ValueFormat::Hex => write!(f, "Hex"),
+        }
+    }
+}
+
 #[derive(Default)]
 struct Leds {
     link: bool,
@@ -557,52 +1075,108 @@ struct Leds {
 
 #[derive(Clone)]
 struct TempConfig {
+    input: Input,
     broadcast: bool,
     controller: String,
+    interface: Option<String>,
+    ///Not editable, kept from the command line
+    bind_ip: Option<Ipv4Addr>,
     port: String,
-    universe: String,
+    merge: MergeMode,
+    routes: Vec<TempRoute>,
     artnet_name: String,
-    serial_name: String,
+    short_name: String,
     custom_break_time: bool,
     break_time: String,
-    remember: bool,
-    
+    loss_kind: LossKind,
+    loss_seconds: String,
+    loss_scene: String,
+    patch: String,
+    output: OutputKind,
+    config: Option<std::path::PathBuf>,
+    ///Not editable, kept from the play command
+    playback: Option<Playback>,
+    ///Not editable, kept from the command line
+    osc: Option<u16>,
+    ///Not editable, kept from the command line
+    http: Option<SocketAddr>,
+    ///Not editable, kept from the command line
+    http_token: Option<String>,
 }
 
 impl Default for TempConfig {
     fn default() -> Self {
         Self {
+            input: Input::default(),
             broadcast: true,
             controller: "0.0.0.0".into(),
+            interface: None,
+            bind_ip: None,
             port: "6454".into(),
-            universe: "0".into(),
+            merge: MergeMode::default(),
+            routes: vec![TempRoute::default()],
             artnet_name: "artnet2opendmx".into(),
-            serial_name: "".into(),
+            short_name: "artnet2opendmx".into(),
             custom_break_time: false,
             break_time: "".into(),
-            remember: false,
+            loss_kind: LossKind::Off,
+            loss_seconds: "3".into(),
+            loss_scene: "".into(),
+            patch: "".into(),
+            output: OutputKind::default(),
+            config: None,
+            playback: None,
+            osc: None,
+            http: None,
+            http_token: None,
         }
     }
 }
 
 impl From<Arguments> for TempConfig {
     fn from(args: Arguments) -> Self {
-        let mut config = Self::default();
+        let mut config = Self {
+            input: args.options.input,
+            ..Self::default()
+        };
         if let Some(controller) = args.options.controller {
-            config.controller = controller;
+            config.controller = controller.to_string();
             config.broadcast = false;
         }
+        config.interface = args.options.interface;
+        config.bind_ip = args.options.bind_ip;
         if let Some(port) = args.options.port {
             config.port = port.to_string();
         }
-        config.universe = args.universe.to_string();
+        if !args.routes.is_empty() {
+            config.routes = args.routes.into_iter().map(|route| TempRoute {
+                universe: route.universe.to_string(),
+                serial_name: route.device_name,
+                output: route.output,
+            }).collect();
+        }
         if let Some(artnet_name) = args.options.name {
             config.artnet_name = artnet_name;
         }
-        config.serial_name = args.device_name;
+        if let Some(short_name) = args.options.short_name {
+            config.short_name = short_name;
+        }
         config.custom_break_time = args.options.break_time.is_some();
         config.break_time = args.options.break_time.map(|time| time.as_millis().to_string()).unwrap_or("25".into());
-        config.remember = args.options.remember;
+        config.merge = args.options.merge;
+        config.loss_kind = LossKind::from(&args.options.loss);
+        match args.options.loss {
+            LossPolicy::Blackout(time) | LossPolicy::Fade(time) => config.loss_seconds = time.as_secs_f32().to_string(),
+            LossPolicy::Scene(path) => config.loss_scene = path.display().to_string(),
+            LossPolicy::Off | LossPolicy::Hold => {},
+        }
+        config.patch = args.options.patch.map(|patch| patch.to_string()).unwrap_or_default();
+        config.output = args.options.output;
+        config.config = args.options.config;
+        config.playback = args.options.playback;
+        config.osc = args.options.osc;
+        config.http = args.options.http;
+        config.http_token = args.options.http_token;
 
         config
     }
@@ -612,35 +1186,133 @@ impl TryInto<Arguments> for TempConfig {
     type Error = String;
 
     fn try_into(self) -> Result<Arguments, Self::Error> {
-        let mut args = Arguments::default();
-        args.universe = self.universe.parse().map_err(|_| "Invalid universe".to_string())?;
-        if args.universe > 32787 {
-            return Err("Universe too high".into());
-        }
-        if self.serial_name.is_empty() {
-            return Err("No device selected".into());
-        }
-        args.device_name = self.serial_name;
+        let mut args = Arguments {
+            routes: self.routes.into_iter().map(TryInto::try_into).collect::<Result<_, _>>()?,
+            ..Default::default()
+        };
+        cli::check_routes(&args.routes)?;
+        args.options.input = self.input;
         if self.broadcast {
             args.options.controller = None;
         } else {
-            let octets = self.controller.split('.').map(|s| s.parse::<u8>()).collect::<Result<Vec<_>, _>>().map_err(|_| "Invalid IP")?;
-            let ip = Ipv4Addr::try_from([octets[0], octets[1], octets[2], octets[3]]).map_err(|_| "Invalid IP")?;
-            args.options.controller = Some(ip.to_string());
+            args.options.controller = Some(self.controller.parse::<SenderFilter>().map_err(|error| error.to_string())?);
         }
+        args.options.interface = self.interface;
+        args.options.bind_ip = self.bind_ip;
         args.options.port = Some(self.port.parse().map_err(|_| "Invalid port".to_string())?);
 
-        if self.artnet_name.bytes().len() > 18 {
+        if self.artnet_name.len() > 18 {
             return Err("Name too long".into());
         }
         args.options.name = Some(self.artnet_name);
+        if self.short_name.len() > 17 {
+            return Err("Short name too long".into());
+        }
+        args.options.short_name = Some(self.short_name);
         if self.custom_break_time {
             args.options.break_time = Some(std::time::Duration::from_millis(self.break_time.parse().map_err(|_| "Invalid break time".to_string())?));
         } else {
             args.options.break_time = None;
         }
-        args.options.remember = self.remember;
+        args.options.merge = self.merge;
+        args.options.output = self.output;
+        args.options.config = self.config;
+        args.options.playback = self.playback;
+        args.options.osc = self.osc;
+        args.options.http = self.http;
+        args.options.http_token = self.http_token;
+        let loss_time = || match self.loss_seconds.parse::<f32>() {
+            Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => Ok(std::time::Duration::from_secs_f32(seconds)),
+            _ => Err("Invalid loss time".to_string()),
+        };
+        args.options.loss = match self.loss_kind {
+            LossKind::Off => LossPolicy::Off,
+            LossKind::Hold => LossPolicy::Hold,
+            LossKind::Blackout => LossPolicy::Blackout(loss_time()?),
+            LossKind::Fade => LossPolicy::Fade(loss_time()?),
+            LossKind::Scene if self.loss_scene.trim().is_empty() => return Err("No scene file".into()),
+            LossKind::Scene => LossPolicy::Scene(self.loss_scene.trim().into()),
+        };
+        args.options.patch = match self.patch.trim().is_empty() {
+            true => None,
+            false => Some(self.patch.parse::<Patch>().map_err(|e| e.to_string())?),
+        };
 
         Ok(args)
     }
 }
+
+///The [`LossPolicy`] variants without their parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LossKind {
+    Off,
+    Hold,
+    Blackout,
+    Fade,
+    Scene,
+}
+
+impl LossKind {
+    const ALL: [LossKind; 5] = [LossKind::Off, LossKind::Hold, LossKind::Blackout, LossKind::Fade, LossKind::Scene];
+}
+
+impl From<&LossPolicy> for LossKind {
+    fn from(policy: &LossPolicy) -> Self {
+        match policy {
+            LossPolicy::Off => LossKind::Off,
+            LossPolicy::Hold => LossKind::Hold,
+            LossPolicy::Blackout(_) => LossKind::Blackout,
+            LossPolicy::Fade(_) => LossKind::Fade,
+            LossPolicy::Scene(_) => LossKind::Scene,
+        }
+    }
+}
+
+impl std::fmt::Display for LossKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LossKind::Off => write!(f, "Stop Output"),
+            LossKind::Hold => write!(f, "Hold Last Look"),
+            LossKind::Blackout => write!(f, "Blackout After"),
+            LossKind::Fade => write!(f, "Fade Out Over"),
+            LossKind::Scene => write!(f, "Failsafe Scene"),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct TempRoute {
+    universe: String,
+    serial_name: String,
+    ///Kept from the command line, the settings only choose the output of all routes
+    output: Option<OutputKind>,
+}
+
+impl Default for TempRoute {
+    fn default() -> Self {
+        Self {
+            universe: "0".into(),
+            serial_name: "".into(),
+            output: None,
+        }
+    }
+}
+
+impl TryInto<Route> for TempRoute {
+    type Error = String;
+
+    fn try_into(self) -> Result<Route, Self::Error> {
+        let universe = self.universe.parse().map_err(|_| "Invalid universe".to_string())?;
+        if universe > 32767 {
+            return Err("Universe too high".into());
+        }
+        if self.serial_name.is_empty() {
+            return Err("No device selected".into());
+        }
+        Ok(Route {
+            universe,
+            device_name: self.serial_name,
+            output: self.output,
+        })
+    }
+}
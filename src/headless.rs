@@ -0,0 +1,137 @@
+//! Runs the bridge without the GUI, controlled by the HTTP API if it's enabled
+use std::{sync::{atomic::{AtomicBool, Ordering}, mpsc::RecvTimeoutError, Arc}, thread};
+
+use log::{error, info, warn};
+
+use crate::api::{ApiCommand, ApiRequest, ApiServer};
+use crate::cli::Arguments;
+use crate::config;
+use crate::runner::{self, RunnerCommand, RunnerCreationError, RunnerHandle, UPDATE_INTERVAL};
+
+///Runs until the runner stops (while the HTTP API is enabled, until Ctrl-C or a termination signal)
+pub fn run(arguments: Arguments) -> Result<(), Box<dyn std::error::Error>> {
+    let stopped = stop_on_signal();
+    let Some(address) = arguments.options.http else {
+        let runner = runner::create_runner(arguments)?;
+        while !stopped.load(Ordering::Relaxed) {
+            if let Err(RecvTimeoutError::Disconnected) = runner.updates().recv_timeout(UPDATE_INTERVAL) {
+                break;
+            }
+        }
+        runner.stop();
+        runner.join();
+        return Ok(());
+    };
+    let api = ApiServer::start(address, || {})?;
+    api.set_arguments(Some(&arguments));
+    let mut bridge = Bridge {
+        runner: Some(runner::create_runner(arguments.clone())?),
+        arguments,
+        blackout: false,
+    };
+    api.set_update(true, None);
+    while !stopped.load(Ordering::Relaxed) {
+        match &bridge.runner {
+            Some(runner) => match runner.updates().recv_timeout(UPDATE_INTERVAL) {
+                Ok(update) => {
+                    if let Some(arguments) = &update.reconfigured {
+                        bridge.arguments = arguments.clone();
+                        api.set_arguments(Some(arguments));
+                    }
+                    // OSC changes it too
+                    bridge.blackout = update.blackout;
+                    api.set_update(true, Some(update));
+                },
+                Err(RecvTimeoutError::Timeout) => {},
+                Err(RecvTimeoutError::Disconnected) => {
+                    bridge.runner = None;
+                    api.set_update(false, None);
+                },
+            },
+            None => thread::sleep(UPDATE_INTERVAL),
+        }
+        for ApiRequest { command, reply } in api.requests() {
+            // A blackout keeps the runner, the others start over without an update
+            let restarts = !matches!(command, ApiCommand::Blackout(_));
+            let result = bridge.apply(command);
+            if let Err(error) = &result {
+                error!("{}", error);
+            }
+            reply.send(result.map_err(|error| error.to_string()));
+            api.set_arguments(Some(&bridge.arguments));
+            if restarts {
+                api.set_update(bridge.runner.is_some(), None);
+            }
+        }
+    }
+    bridge.stop();
+    Ok(())
+}
+
+///Set on Ctrl-C or a termination signal, so the runner gets stopped and finishes a recording
+fn stop_on_signal() -> Arc<AtomicBool> {
+    let stopped = Arc::new(AtomicBool::new(false));
+    let handler_stopped = stopped.clone();
+    if let Err(error) = ctrlc::set_handler(move || handler_stopped.store(true, Ordering::Relaxed)) {
+        warn!("Couldn't handle Ctrl-C, the end of a recording might get lost: {}", error);
+    }
+    stopped
+}
+
+///The runner and the settings it got started with
+struct Bridge {
+    runner: Option<RunnerHandle>,
+    arguments: Arguments,
+    ///Kept over restarts
+    blackout: bool,
+}
+
+impl Bridge {
+    fn apply(&mut self, command: ApiCommand) -> Result<(), RunnerCreationError> {
+        match command {
+            ApiCommand::Start if self.runner.is_some() => Ok(()),
+            ApiCommand::Start | ApiCommand::Restart => self.restart(),
+            ApiCommand::Stop => {
+                self.stop();
+                Ok(())
+            },
+            ApiCommand::Blackout(blackout) => {
+                self.blackout = blackout;
+                if let Some(runner) = &self.runner {
+                    runner.send(RunnerCommand::Blackout(blackout));
+                }
+                Ok(())
+            },
+            ApiCommand::Configure(arguments) => {
+                if let Some(path) = &arguments.options.config {
+                    match config::save(path, &arguments) {
+                        Ok(_) => info!("Saved settings to \"{}\"", path.display()),
+                        Err(error) => error!("{}", error),
+                    }
+                }
+                self.arguments = *arguments;
+                match self.runner {
+                    Some(_) => self.restart(),
+                    None => Ok(()),
+                }
+            },
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(runner) = self.runner.take() {
+            runner.stop();
+            runner.join();
+        }
+    }
+
+    fn restart(&mut self) -> Result<(), RunnerCreationError> {
+        self.stop();
+        let runner = runner::create_runner(self.arguments.clone())?;
+        if self.blackout {
+            runner.send(RunnerCommand::Blackout(true));
+        }
+        self.runner = Some(runner);
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 mod cli;
+mod config;
+mod device;
 use cli::{Command, HELP_TEXT};
 
 mod gui;
@@ -6,6 +8,25 @@ mod gui;
 use gui::run_app;
 
 mod runner;
+mod api;
+mod artnet;
+mod enttec;
+mod filter;
+mod headless;
+mod merge;
+mod metrics;
+mod network;
+mod osc;
+mod output;
+mod overrides;
+mod patch;
+mod playback;
+mod sacn;
+mod scene;
+mod show;
+mod stats;
+mod stream;
+mod supervisor;
 
 use log::SetLoggerError;
 use serialport::available_ports;
@@ -24,7 +45,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
     };
     match command {
-        Command::List => {
+        Command::List { probe } => {
             println!("Available ports:");
             let ports = match available_ports() {
                 Err(error) => {
@@ -35,12 +56,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 Ok(ports) => ports,
             };
             for port in ports {
-                if let serialport::SerialPortType::UsbPort(_) = port.port_type {
-                    println!("  - \"{}\" (USB)", port.port_name);
+                if let serialport::SerialPortType::UsbPort(info) = &port.port_type {
+                    match probe.then(|| enttec::detect(&port.port_name)).flatten() {
+                        Some(widget) => println!("  - \"{}\" (USB, {}, {})", port.port_name, device::describe(info), widget),
+                        None => println!("  - \"{}\" (USB, {})", port.port_name, device::describe(info)),
+                    }
+                    println!("    {}", device::DeviceSelector::for_port(&port));
                     continue;
                 }
                 println!("  - \"{}\"", port.port_name);
             }
+            println!("Network interfaces:");
+            for interface in network::list() {
+                println!("  - {}", interface);
+            }
             Ok(())
         },
         Command::Help =>  {
@@ -52,18 +81,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Ok(())
         },
 
+        Command::Record(args) => {
+            initialize_logger(args.options.verbose)?;
+            if let Err(error) = runner::record(args) {
+                eprintln!("Couldn't record: {}", error);
+                eprintln!("Exiting...");
+                std::process::exit(1);
+            }
+            Ok(())
+        },
         Command::Cli(args) => {
             initialize_logger(args.options.verbose)?;
-            let runner_update_reciever = match runner::create_runner(args) {
-                Ok(runner_update_reciever) => runner_update_reciever,
-                Err(error) => {
-                    eprintln!("Couldn't create runner: {}", error);
-                    eprintln!("Exiting...");
-                    std::process::exit(1);
-                },
-            };
-
-            for _ in runner_update_reciever {
+            if let Err(error) = headless::run(args) {
+                eprintln!("Couldn't create runner: {}", error);
+                eprintln!("Exiting...");
+                std::process::exit(1);
             }
             Ok(())
         }
@@ -0,0 +1,195 @@
+//! Merging of multiple senders on the same universe
+use std::{net::SocketAddr, time::{Duration, Instant}};
+
+use crate::cli::MergeMode;
+
+///Art-Net cancels a merge if one of the sources stops for 10 seconds
+pub const SOURCE_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Source {
+    address: SocketAddr,
+    channels: [u8; 512],
+    last_seen: Instant,
+}
+
+pub struct Merger {
+    mode: MergeMode,
+    sources: Vec<Source>,
+    output: [u8; 512],
+    cancel_pending: bool,
+    ///The only sender accepted after a cancelled merge
+    exclusive: Option<SocketAddr>,
+}
+
+impl Merger {
+    pub fn new(mode: MergeMode) -> Self {
+        Self {
+            mode,
+            sources: Vec::new(),
+            output: [0; 512],
+            cancel_pending: false,
+            exclusive: None,
+        }
+    }
+
+    ///Adds a frame of `sender` to the merge and returns the merged output
+    pub fn merge(&mut self, sender: SocketAddr, channels: [u8; 512]) -> [u8; 512] {
+        if self.cancel_pending {
+            self.cancel_pending = false;
+            self.sources.clear();
+            self.exclusive = Some(sender);
+        }
+        if self.exclusive.is_some_and(|exclusive| exclusive != sender) {
+            return self.output;
+        }
+        let now = Instant::now();
+        match self.sources.iter_mut().find(|source| source.address == sender) {
+            Some(source) => {
+                if self.mode == MergeMode::Ltp {
+                    for ((output, old), new) in self.output.iter_mut().zip(source.channels.iter()).zip(channels.iter()) {
+                        if old != new {
+                            *output = *new;
+                        }
+                    }
+                }
+                source.channels = channels;
+                source.last_seen = now;
+            },
+            None => {
+                if self.mode == MergeMode::Ltp {
+                    self.output = channels;
+                }
+                self.sources.push(Source {
+                    address: sender,
+                    channels,
+                    last_seen: now,
+                });
+            },
+        }
+        if self.sources.len() == 1 {
+            self.output = channels;
+        } else if self.mode == MergeMode::Htp {
+            self.output = self.htp();
+        }
+        self.output
+    }
+
+    ///Removes all timed out sources. Returns `true` if the output changed.
+    pub fn expire(&mut self) -> bool {
+        let count = self.sources.len();
+        self.sources.retain(|source| source.last_seen.elapsed() < SOURCE_TIMEOUT);
+        if self.exclusive.is_some_and(|exclusive| !self.sources.iter().any(|source| source.address == exclusive)) {
+            self.exclusive = None;
+        }
+        if self.sources.len() == count || self.sources.is_empty() {
+            return false;
+        }
+        let output = match (self.mode, self.sources.len()) {
+            (_, 1) => self.sources[0].channels,
+            (MergeMode::Htp, _) => self.htp(),
+            (MergeMode::Ltp, _) => self.output,
+        };
+        let changed = output != self.output;
+        self.output = output;
+        changed
+    }
+
+    pub fn output(&self) -> [u8; 512] {
+        self.output
+    }
+
+    pub fn mode(&self) -> MergeMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: MergeMode) {
+        self.mode = mode;
+        if mode == MergeMode::Htp && self.is_merging() {
+            self.output = self.htp();
+        }
+    }
+
+    ///Hands the universe to the sender of the next frame
+    pub fn cancel_merge(&mut self) {
+        self.cancel_pending = true;
+    }
+
+    pub fn source_count(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_merging(&self) -> bool {
+        self.sources.len() > 1
+    }
+
+    fn htp(&self) -> [u8; 512] {
+        let mut output = [0; 512];
+        for source in &self.sources {
+            output.iter_mut().zip(source.channels.iter()).for_each(|(a, b)| *a = (*a).max(*b));
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sender(port: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], port))
+    }
+
+    fn frame(values: &[(usize, u8)]) -> [u8; 512] {
+        let mut frame = [0; 512];
+        for (index, value) in values {
+            frame[*index] = *value;
+        }
+        frame
+    }
+
+    #[test]
+    fn passes_a_single_sender_through() {
+        let mut merger = Merger::new(MergeMode::Htp);
+        merger.merge(sender(1), frame(&[(0, 255)]));
+        assert_eq!(merger.merge(sender(1), frame(&[(1, 10)])), frame(&[(1, 10)]));
+        assert!(!merger.is_merging());
+    }
+
+    #[test]
+    fn htp_takes_the_highest_value() {
+        let mut merger = Merger::new(MergeMode::Htp);
+        merger.merge(sender(1), frame(&[(0, 200), (1, 10)]));
+        assert_eq!(merger.merge(sender(2), frame(&[(0, 100), (1, 50)])), frame(&[(0, 200), (1, 50)]));
+        assert_eq!(merger.merge(sender(1), frame(&[(1, 10)])), frame(&[(0, 100), (1, 50)]));
+        assert_eq!(merger.source_count(), 2);
+    }
+
+    #[test]
+    fn ltp_takes_the_latest_change() {
+        let mut merger = Merger::new(MergeMode::Ltp);
+        merger.merge(sender(1), frame(&[(0, 200), (1, 10)]));
+        assert_eq!(merger.merge(sender(2), frame(&[(0, 100)])), frame(&[(0, 100)]));
+        // Only the channel sender 1 changed goes back to it
+        assert_eq!(merger.merge(sender(1), frame(&[(0, 200), (1, 20)])), frame(&[(0, 100), (1, 20)]));
+    }
+
+    #[test]
+    fn switching_to_htp_merges_again() {
+        let mut merger = Merger::new(MergeMode::Ltp);
+        merger.merge(sender(1), frame(&[(0, 200)]));
+        merger.merge(sender(2), frame(&[(1, 100)]));
+        merger.set_mode(MergeMode::Htp);
+        assert_eq!(merger.output(), frame(&[(0, 200), (1, 100)]));
+    }
+
+    #[test]
+    fn cancelled_merge_hands_the_universe_to_the_next_sender() {
+        let mut merger = Merger::new(MergeMode::Htp);
+        merger.merge(sender(1), frame(&[(0, 200)]));
+        merger.merge(sender(2), frame(&[(1, 100)]));
+        merger.cancel_merge();
+        assert_eq!(merger.merge(sender(2), frame(&[(1, 50)])), frame(&[(1, 50)]));
+        assert_eq!(merger.merge(sender(1), frame(&[(0, 255)])), frame(&[(1, 50)]));
+        assert_eq!(merger.source_count(), 1);
+    }
+}
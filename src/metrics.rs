@@ -0,0 +1,93 @@
+//! Prometheus metrics of the runner, served by the HTTP API at `/metrics`
+use std::fmt::Write;
+
+use crate::cli::Arguments;
+use crate::runner::RunnerUpdate;
+use crate::supervisor::DeviceState;
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+const PREFIX: &str = "artnet2dmx";
+
+///Renders the metrics in the Prometheus text format
+pub fn render(running: bool, update: Option<&RunnerUpdate>, arguments: Option<&Arguments>) -> String {
+    let mut metrics = Metrics(String::new());
+    metrics.header("up", "gauge", "Whether the bridge is running");
+    metrics.value("up", "", running as u8);
+    let Some(update) = update else {
+        return metrics.0;
+    };
+    let statistics = &update.statistics;
+    metrics.header("packets_received_total", "counter", "Recieved Art-Net and sACN dmx packets, without the frames of a played back show");
+    metrics.value("packets_received_total", "", statistics.packets);
+    metrics.header("packets_ignored_total", "counter", "Recieved dmx packets which weren't sent to a device");
+    metrics.value("packets_ignored_total", "reason=\"other_universe\"", statistics.other_universe_packets);
+    metrics.value("packets_ignored_total", "reason=\"rejected_controller\"", statistics.rejected_packets);
+    metrics.header("frames_sent_total", "counter", "Frames written to the dmx interfaces");
+    metrics.value("frames_sent_total", "", statistics.frames);
+    metrics.header("serial_errors_total", "counter", "Failed writes to the dmx interfaces");
+    metrics.value("serial_errors_total", "", statistics.serial_failures);
+    metrics.header("reconnects_total", "counter", "Dmx interfaces which came back after getting disconnected");
+    metrics.value("reconnects_total", "", statistics.reconnects);
+    metrics.header("sender_packets_total", "counter", "Recieved dmx packets per sender since the start, senders are dropped a minute after their last packet");
+    for sender in &statistics.senders {
+        metrics.value("sender_packets_total", &format!("sender=\"{}\",protocol=\"{}\",rejected=\"{}\"", sender.ip, sender.protocol, sender.rejected), sender.packets);
+    }
+    metrics.header("synchronous", "gauge", "Whether the output is only committed on ArtSync");
+    metrics.value("synchronous", "", update.synchronous as u8);
+    metrics.header("blackout", "gauge", "Whether all outputs are forced to black");
+    metrics.value("blackout", "", update.blackout as u8);
+    metrics.header("master", "gauge", "The level all outputs are scaled to");
+    metrics.value("master", "", update.master);
+
+    let routes = update.routes.iter().enumerate().map(|(i, route)| {
+        let device = arguments.and_then(|arguments| arguments.routes.get(i)).map(|route| route.device_name.as_str()).unwrap_or_default();
+        (route, format!("universe=\"{}\",device=\"{}\"", route.universe, escape(device)))
+    }).collect::<Vec<_>>();
+    metrics.header("device_connected", "gauge", "Whether the dmx interface of a route is connected");
+    for (route, labels) in &routes {
+        metrics.value("device_connected", labels, (route.device == DeviceState::Connected) as u8);
+    }
+    metrics.header("signal_lost", "gauge", "Whether a route got no valid frame for 4 seconds");
+    for (route, labels) in &routes {
+        metrics.value("signal_lost", labels, route.signal_lost as u8);
+    }
+    metrics.header("sources", "gauge", "The number of senders merged into a route");
+    for (route, labels) in &routes {
+        metrics.value("sources", labels, route.sources);
+    }
+    metrics.header("last_packet_age_seconds", "gauge", "The time since the last frame of a route arrived");
+    for (route, labels) in &routes {
+        if let Some((instant, _)) = route.last_packet {
+            metrics.value("last_packet_age_seconds", labels, instant.elapsed().as_secs_f32());
+        }
+    }
+    metrics.header("sender_info", "gauge", "The latest sender of a route");
+    for (route, labels) in &routes {
+        if let Some((_, sender)) = route.last_packet {
+            metrics.value("sender_info", &format!("{},sender=\"{}\"", labels, sender.ip()), 1);
+        }
+    }
+    metrics.0
+}
+
+struct Metrics(String);
+
+impl Metrics {
+    fn header(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.0, "# HELP {PREFIX}_{name} {help}");
+        let _ = writeln!(self.0, "# TYPE {PREFIX}_{name} {kind}");
+    }
+
+    fn value(&mut self, name: &str, labels: &str, value: impl std::fmt::Display) {
+        let _ = match labels.is_empty() {
+            true => writeln!(self.0, "{PREFIX}_{name} {value}"),
+            false => writeln!(self.0, "{PREFIX}_{name}{{{labels}}} {value}"),
+        };
+    }
+}
+
+///Escapes a label value
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
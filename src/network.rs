@@ -0,0 +1,71 @@
+//! The network interfaces the listeners can be bound to
+use std::{fmt::{Display, Formatter}, net::Ipv4Addr};
+
+use log::warn;
+use socket2::Socket;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub ip: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+}
+
+impl NetworkInterface {
+    ///The directed broadcast address of the subnet, e.g. `2.255.255.255` for `2.0.0.1/8`
+    pub fn broadcast(&self) -> Ipv4Addr {
+        Ipv4Addr::from(u32::from(self.ip) | !u32::from(self.netmask))
+    }
+}
+
+impl Display for NetworkInterface {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}/{})", self.name, self.ip, u32::from(self.netmask).count_ones())
+    }
+}
+
+///All interfaces with an IPv4 address, an interface with several addresses is listed once per address
+pub fn list() -> Vec<NetworkInterface> {
+    let interfaces = match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces,
+        Err(error) => {
+            warn!("Couldn't list network interfaces: {}", error);
+            return Vec::new();
+        },
+    };
+    interfaces.into_iter().filter_map(|interface| match interface.addr {
+        if_addrs::IfAddr::V4(address) => Some(NetworkInterface {
+            name: interface.name,
+            ip: address.ip,
+            netmask: address.netmask,
+        }),
+        if_addrs::IfAddr::V6(_) => None,
+    }).collect()
+}
+
+///The first address of the interface called `name`
+pub fn find(name: &str) -> Option<NetworkInterface> {
+    list().into_iter().find(|interface| interface.name == name)
+}
+
+///The interface which has the address `ip`
+pub fn find_by_ip(ip: Ipv4Addr) -> Option<NetworkInterface> {
+    list().into_iter().find(|interface| interface.ip == ip)
+}
+
+///Restricts `socket` to `device`, `false` if that isn't possible (only Linux supports it)
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn bind_device(socket: &Socket, device: &NetworkInterface) -> bool {
+    match socket.bind_device(Some(device.name.as_bytes())) {
+        Ok(()) => true,
+        Err(error) => {
+            warn!("Couldn't bind to interface \"{}\": {}", device.name, error);
+            false
+        },
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn bind_device(_socket: &Socket, _device: &NetworkInterface) -> bool {
+    false
+}
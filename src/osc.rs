@@ -0,0 +1,230 @@
+//! A minimal OSC reciever for show control (e.g. QLab or TouchOSC)
+use std::{net::{SocketAddr, SocketAddrV4, UdpSocket}, sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc}, thread::{self, JoinHandle}};
+
+use log::{debug, warn};
+use socket2::Socket;
+
+use crate::filter::SenderFilter;
+use crate::network::{self, NetworkInterface};
+use crate::runner::{self, InputEvent, Protocol, Rejections, RunnerCommand, LISTENER_TIMEOUT};
+
+const NAMESPACE: &str = "/artnet2dmx";
+
+///An OSC message with its numeric arguments
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub arguments: Vec<f32>,
+}
+
+///Parses a message or the messages of a bundle. Returns `None` if the packet is malformed or has other arguments.
+pub fn parse(buffer: &[u8]) -> Option<Vec<OscMessage>> {
+    if buffer.starts_with(b"#bundle\0") {
+        let mut messages = Vec::new();
+        // Skips the time tag
+        let mut rest = buffer.get(16..)?;
+        while !rest.is_empty() {
+            let size = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+            messages.extend(parse(rest.get(4..4 + size)?)?);
+            rest = &rest[4 + size..];
+        }
+        return Some(messages);
+    }
+    let (address, rest) = read_string(buffer)?;
+    if !address.starts_with('/') {
+        return None;
+    }
+    let (tags, mut rest) = match read_string(rest) {
+        Some((tags, rest)) if tags.starts_with(',') => (tags, rest),
+        // Old senders leave out the type tags of messages without arguments
+        _ if rest.is_empty() => (",".into(), rest),
+        _ => return None,
+    };
+    let mut arguments = Vec::new();
+    for tag in tags.chars().skip(1) {
+        let argument = match tag {
+            'i' => i32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as f32,
+            'f' => f32::from_be_bytes(rest.get(..4)?.try_into().ok()?),
+            'h' => i64::from_be_bytes(rest.get(..8)?.try_into().ok()?) as f32,
+            'd' => f64::from_be_bytes(rest.get(..8)?.try_into().ok()?) as f32,
+            'T' => 1.0,
+            'F' => 0.0,
+            _ => return None,
+        };
+        rest = &rest[match tag {
+            'i' | 'f' => 4,
+            'h' | 'd' => 8,
+            _ => 0,
+        }..];
+        arguments.push(argument);
+    }
+    Some(vec![OscMessage { address, arguments }])
+}
+
+///Reads a null terminated string, padded to a multiple of 4 bytes
+fn read_string(buffer: &[u8]) -> Option<(String, &[u8])> {
+    let length = buffer.iter().position(|byte| *byte == 0)?;
+    let string = std::str::from_utf8(&buffer[..length]).ok()?.to_string();
+    let padded = (length / 4 + 1) * 4;
+    Some((string, buffer.get(padded..)?))
+}
+
+impl OscMessage {
+    ///The runner command of the message, `None` if it's for someone else
+    pub fn command(&self) -> Option<Result<RunnerCommand, String>> {
+        let path = self.address.strip_prefix(NAMESPACE)?;
+        let argument = || self.arguments.first().copied().filter(|argument| argument.is_finite()).ok_or_else(|| format!("{} needs an argument", self.address));
+        let channel = |universe: Option<u16>, channel: &str| {
+            let channel = match channel.parse::<u16>() {
+                Ok(channel @ 1..=512) => channel,
+                _ => return Err(format!("Invalid channel \"{channel}\" (1-512)")),
+            };
+            let value = argument()?;
+            Ok(RunnerCommand::Channel {
+                universe,
+                channel,
+                value: (value >= 0.0).then(|| value.round().min(255.0) as u8),
+            })
+        };
+        let segments = path.split('/').skip(1).collect::<Vec<_>>();
+        Some(match segments.as_slice() {
+            ["blackout"] => argument().map(|value| RunnerCommand::Blackout(value != 0.0)),
+            ["master"] => argument().map(|value| RunnerCommand::Master(value.clamp(0.0, 1.0))),
+            ["channel", number] => channel(None, number),
+            ["universe", universe, "channel", number] => match universe.parse::<u16>() {
+                Ok(universe) => channel(Some(universe), number),
+                Err(_) => Err(format!("Invalid universe \"{universe}\"")),
+            },
+            ["release"] => Ok(RunnerCommand::ReleaseChannels),
+            _ => Err(format!("Unknown address \"{}\"", self.address)),
+        })
+    }
+}
+
+///Starts a thread recieving OSC messages on `address` and forwarding their commands to `tx`
+///
+///Only `device` (if given) recieves them and only the senders allowed by `controller` may send commands.
+pub fn start_reciever(mut address: SocketAddrV4, device: Option<&NetworkInterface>, controller: Option<SenderFilter>, tx: mpsc::Sender<InputEvent>, running: Arc<AtomicBool>) -> std::io::Result<JoinHandle<()>> {
+    let socket = Socket::new(socket2::Domain::IPV4, socket2::Type::DGRAM, Some(socket2::Protocol::UDP))?;
+    if let Some(device) = device {
+        // Without binding to the device, the address of the device keeps the others out
+        if !network::bind_device(&socket, device) && address.ip().is_unspecified() {
+            address.set_ip(device.ip);
+        }
+    }
+    socket.bind(&SocketAddr::from(address).into())?;
+    let socket = UdpSocket::from(socket);
+    socket.set_read_timeout(Some(LISTENER_TIMEOUT))?;
+
+    Ok(thread::spawn(move || {
+        let mut buffer = [0; 1536];
+        let mut rejections = Rejections::new(Protocol::Osc);
+        while running.load(Ordering::Relaxed) {
+            if rejections.report(&tx).is_err() {
+                debug!("OSC reciever disconnected");
+                break;
+            }
+            let (size, sender) = match socket.recv_from(&mut buffer) {
+                Ok(packet) => packet,
+                Err(error) => {
+                    runner::listener_error("OSC", error);
+                    continue;
+                },
+            };
+            if controller.as_ref().is_some_and(|controller| !controller.allows(sender.ip())) {
+                rejections.count(sender.ip());
+                continue;
+            }
+            let Some(messages) = parse(&buffer[..size]) else {
+                debug!("Dropping malformed OSC packet from {}", sender);
+                continue;
+            };
+            for message in messages {
+                debug!("Recieved OSC {} {:?} from {}", message.address, message.arguments, sender);
+                let command = match message.command() {
+                    Some(Ok(command)) => command,
+                    Some(Err(error)) => {
+                        warn!("Ignoring OSC message from {}: {}", sender, error);
+                        continue;
+                    },
+                    None => continue,
+                };
+                if tx.send(InputEvent::Command(command)).is_err() {
+                    debug!("OSC reciever disconnected");
+                    return;
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///A message with its arguments already encoded
+    fn packet(address: &str, tags: &str, arguments: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        for string in [address, tags] {
+            packet.extend_from_slice(string.as_bytes());
+            packet.resize((packet.len() / 4 + 1) * 4, 0);
+        }
+        packet.extend_from_slice(arguments);
+        packet
+    }
+
+    fn parse_one(packet: &[u8]) -> OscMessage {
+        let mut messages = parse(packet).unwrap();
+        assert_eq!(messages.len(), 1);
+        messages.remove(0)
+    }
+
+    #[test]
+    fn parses_arguments() {
+        let arguments = [&7i32.to_be_bytes()[..], &0.5f32.to_be_bytes(), &(-2i64).to_be_bytes(), &0.25f64.to_be_bytes()].concat();
+        let message = parse_one(&packet("/artnet2dmx/master", ",ifhdTF", &arguments));
+        assert_eq!(message.address, "/artnet2dmx/master");
+        assert_eq!(message.arguments, [7.0, 0.5, -2.0, 0.25, 1.0, 0.0]);
+        // An address of 4 characters gets a whole word of padding
+        assert_eq!(parse_one(&packet("/abc", ",i", &1i32.to_be_bytes())).arguments, [1.0]);
+        // Without type tags
+        assert!(parse_one(b"/artnet2dmx/release\0").arguments.is_empty());
+    }
+
+    #[test]
+    fn unpacks_bundles() {
+        let mut bundle = b"#bundle\0".to_vec();
+        bundle.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        for message in [packet("/a", ",i", &1i32.to_be_bytes()), packet("/b", ",", &[])] {
+            bundle.extend_from_slice(&(message.len() as u32).to_be_bytes());
+            bundle.extend_from_slice(&message);
+        }
+        let messages = parse(&bundle).unwrap();
+        assert_eq!(messages.iter().map(|message| message.address.as_str()).collect::<Vec<_>>(), ["/a", "/b"]);
+        assert_eq!(messages[0].arguments, [1.0]);
+    }
+
+    #[test]
+    fn rejects_malformed_packets() {
+        assert_eq!(parse(b"artnet2dmx\0\0"), None);
+        assert_eq!(parse(b"/a\0\0,s\0\0text"), None);
+        // Missing arguments
+        assert_eq!(parse(&packet("/a", ",if", &1i32.to_be_bytes())), None);
+        // Bundle element longer than the packet
+        assert_eq!(parse(&[&b"#bundle\0"[..], &[0; 8], &100u32.to_be_bytes()].concat()), None);
+    }
+
+    #[test]
+    fn maps_addresses_to_commands() {
+        let command = |address: &str, arguments: &[f32]| OscMessage { address: address.into(), arguments: arguments.to_vec() }.command();
+        assert!(matches!(command("/artnet2dmx/blackout", &[1.0]), Some(Ok(RunnerCommand::Blackout(true)))));
+        assert!(matches!(command("/artnet2dmx/master", &[2.0]), Some(Ok(RunnerCommand::Master(master))) if master == 1.0));
+        assert!(matches!(command("/artnet2dmx/channel/12", &[300.0]), Some(Ok(RunnerCommand::Channel { universe: None, channel: 12, value: Some(255) }))));
+        assert!(matches!(command("/artnet2dmx/universe/3/channel/1", &[-1.0]), Some(Ok(RunnerCommand::Channel { universe: Some(3), channel: 1, value: None }))));
+        assert!(matches!(command("/artnet2dmx/release", &[]), Some(Ok(RunnerCommand::ReleaseChannels))));
+        assert!(matches!(command("/artnet2dmx/channel/513", &[1.0]), Some(Err(_))));
+        assert!(matches!(command("/artnet2dmx/master", &[]), Some(Err(_))));
+        assert!(matches!(command("/artnet2dmx/unknown", &[1.0]), Some(Err(_))));
+        assert!(command("/other/blackout", &[1.0]).is_none());
+    }
+}
@@ -0,0 +1,399 @@
+//! The dmx outputs a route can write to
+use std::{fmt::{Display, Formatter}, fs::{File, OpenOptions}, io::{self, Write}, thread, time::{Duration, Instant}};
+
+use open_dmx::{error::DMXDisconnectionError, DMXSerial};
+
+use crate::enttec::EnttecPro;
+
+///How long opening a device is retried, a stopped runner might still be releasing it
+const DEVICE_RELEASE_TIMEOUT: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputKind {
+    ///An Open DMX (FTDI) interface
+    #[default]
+    Serial,
+    ///An Enttec DMX USB Pro or compatible widget
+    Pro,
+    ///Discards the frames, only keeps the latest one in memory
+    Null,
+    ///Appends every frame as 512 raw bytes to the file named like the device
+    File,
+    ///Writes the start code and channels of every frame to a pseudo-terminal, linked at the device name
+    Pty,
+}
+
+impl OutputKind {
+    pub const ALL: [OutputKind; 5] = [OutputKind::Serial, OutputKind::Pro, OutputKind::Null, OutputKind::File, OutputKind::Pty];
+
+    ///`true` for the outputs which need a device
+    pub fn is_device(&self) -> bool {
+        matches!(self, OutputKind::Serial | OutputKind::Pro)
+    }
+
+    ///The name used on the command line
+    pub fn id(&self) -> &'static str {
+        match self {
+            OutputKind::Serial => "serial",
+            OutputKind::Pro => "pro",
+            OutputKind::Null => "null",
+            OutputKind::File => "file",
+            OutputKind::Pty => "pty",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputKind {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        OutputKind::ALL.into_iter().find(|kind| kind.id() == s).ok_or_else(|| format!("Invalid output \"{s}\" (expected serial, pro, null, file or pty)").into())
+    }
+}
+
+impl Display for OutputKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputKind::Serial => write!(f, "Serial"),
+            OutputKind::Pro => write!(f, "DMX USB Pro"),
+            OutputKind::Null => write!(f, "Null"),
+            OutputKind::File => write!(f, "File"),
+            OutputKind::Pty => write!(f, "Pseudo-terminal"),
+        }
+    }
+}
+
+///A dmx interface the runner writes frames to
+pub trait DmxOutput: Send {
+    fn name(&self) -> &str;
+    fn set_channels(&mut self, channels: [u8; 512]);
+    ///Sends the channels. Asynchronous outputs refresh on their own, so this only checks they still do.
+    fn update(&mut self) -> Result<(), OutputError>;
+    ///`false` once the interface got disconnected
+    fn is_connected(&self) -> bool;
+    fn is_sync(&self) -> bool;
+    ///Only sends on [`DmxOutput::update`]
+    fn set_sync(&mut self);
+    ///Keeps refreshing the channels continuously
+    fn set_async(&mut self) -> Result<(), OutputError>;
+    ///The minimum time between two dmx packets
+    fn set_packet_time(&mut self, time: Duration);
+}
+
+///Opens the output `name` in sync mode. Files and terminals in a missing directory count as [`OutputError::NotFound`].
+pub fn open(kind: OutputKind, name: &str) -> Result<Box<dyn DmxOutput>, OutputError> {
+    Ok(match kind {
+        OutputKind::Serial => Box::new(retry_open(|| DMXSerial::open_sync(name))?),
+        OutputKind::Pro => Box::new(retry_open(|| EnttecPro::open(name))?),
+        OutputKind::Null => Box::new(MemoryOutput::new(name, None)),
+        OutputKind::File => Box::new(MemoryOutput::new(name, Some(append(name).map_err(|error| missing(name, error.into()))?))),
+        #[cfg(unix)]
+        OutputKind::Pty => Box::new(PtyOutput::open(name).map_err(|error| missing(name, error))?),
+        #[cfg(not(unix))]
+        OutputKind::Pty => return Err(OutputError::Unsupported),
+    })
+}
+
+///Turns a missing directory into [`OutputError::NotFound`], so the output is waited for
+fn missing(name: &str, error: OutputError) -> OutputError {
+    match error {
+        OutputError::Io(error) if error.kind() == io::ErrorKind::NotFound => OutputError::NotFound(name.into()),
+        error => error,
+    }
+}
+
+///Opens a serial device, retrying while a stopped runner might still hold it
+fn retry_open<T>(open: impl Fn() -> Result<T, serialport::Error>) -> Result<T, serialport::Error> {
+    let start = Instant::now();
+    loop {
+        match open() {
+            Err(_) if start.elapsed() < DEVICE_RELEASE_TIMEOUT => thread::sleep(Duration::from_millis(10)),
+            result => return result,
+        }
+    }
+}
+
+impl DmxOutput for DMXSerial {
+    fn name(&self) -> &str {
+        DMXSerial::name(self)
+    }
+
+    fn set_channels(&mut self, channels: [u8; 512]) {
+        DMXSerial::set_channels(self, channels)
+    }
+
+    fn update(&mut self) -> Result<(), OutputError> {
+        match DMXSerial::is_sync(self) {
+            true => DMXSerial::update(self)?,
+            false => self.check_agent()?,
+        }
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.check_agent().is_ok()
+    }
+
+    fn is_sync(&self) -> bool {
+        DMXSerial::is_sync(self)
+    }
+
+    fn set_sync(&mut self) {
+        DMXSerial::set_sync(self)
+    }
+
+    fn set_async(&mut self) -> Result<(), OutputError> {
+        DMXSerial::set_async(self);
+        Ok(self.update_async()?)
+    }
+
+    fn set_packet_time(&mut self, time: Duration) {
+        DMXSerial::set_packet_time(self, time)
+    }
+}
+
+fn append(path: &str) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+///Keeps the frames in memory and optionally appends them to a file whenever they change
+pub struct MemoryOutput {
+    name: String,
+    channels: [u8; 512],
+    sync: bool,
+    file: Option<File>,
+    ///The frame last appended to the file
+    written: Option<[u8; 512]>,
+}
+
+impl MemoryOutput {
+    pub fn new(name: &str, file: Option<File>) -> Self {
+        Self {
+            name: name.into(),
+            channels: [0; 512],
+            sync: true,
+            file,
+            written: None,
+        }
+    }
+}
+
+impl DmxOutput for MemoryOutput {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_channels(&mut self, channels: [u8; 512]) {
+        self.channels = channels;
+    }
+
+    fn update(&mut self) -> Result<(), OutputError> {
+        if let Some(file) = &mut self.file {
+            if self.written != Some(self.channels) {
+                file.write_all(&self.channels)?;
+                self.written = Some(self.channels);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    fn is_sync(&self) -> bool {
+        self.sync
+    }
+
+    fn set_sync(&mut self) {
+        self.sync = true;
+    }
+
+    fn set_async(&mut self) -> Result<(), OutputError> {
+        self.sync = false;
+        Ok(())
+    }
+
+    fn set_packet_time(&mut self, _time: Duration) {}
+}
+
+///Writes the serial data stream (start code and channels, without the break) to a pseudo-terminal
+///
+///The terminal is linked at the device name, so it can be opened like a real interface.
+#[cfg(unix)]
+pub struct PtyOutput {
+    output: MemoryOutput,
+    master: serialport::TTYPort,
+    ///The path of the terminal the link points at
+    terminal: String,
+    ///Held open so writes don't fail while no one is reading
+    _slave: serialport::TTYPort,
+}
+
+#[cfg(unix)]
+impl PtyOutput {
+    ///Frames are dropped if no one reads them for this long
+    const WRITE_TIMEOUT: Duration = Duration::from_millis(10);
+
+    pub fn open(name: &str) -> Result<Self, OutputError> {
+        use serialport::SerialPort;
+
+        let (mut master, slave) = serialport::TTYPort::pair()?;
+        master.set_timeout(Self::WRITE_TIMEOUT)?;
+        let terminal = slave.name().unwrap_or_default();
+        // Only a link left behind by an earlier run gets replaced, anything else at the name is kept
+        if std::fs::read_link(name).is_ok_and(|target| Self::is_terminal(&target)) {
+            std::fs::remove_file(name)?;
+        }
+        std::os::unix::fs::symlink(&terminal, name)?;
+        log::info!("Pseudo-terminal \"{}\" linked at \"{}\"", terminal, name);
+        Ok(Self {
+            output: MemoryOutput::new(name, None),
+            master,
+            terminal,
+            _slave: slave,
+        })
+    }
+
+    ///Whether `path` is a pseudo-terminal (`/dev/pts/N` on Linux, `/dev/ttysN` on macOS)
+    fn is_terminal(path: &std::path::Path) -> bool {
+        path.starts_with("/dev/pts") || path.to_str().is_some_and(|path| path.starts_with("/dev/ttys"))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for PtyOutput {
+    fn drop(&mut self) {
+        // The link may have been replaced in the meantime
+        if std::fs::read_link(&self.output.name).is_ok_and(|target| target == std::path::Path::new(&self.terminal)) {
+            let _ = std::fs::remove_file(&self.output.name);
+        }
+    }
+}
+
+#[cfg(unix)]
+impl DmxOutput for PtyOutput {
+    fn name(&self) -> &str {
+        self.output.name()
+    }
+
+    fn set_channels(&mut self, channels: [u8; 512]) {
+        self.output.set_channels(channels);
+    }
+
+    fn update(&mut self) -> Result<(), OutputError> {
+        let mut packet = [0; 513]; // start code + 512 channels
+        packet[1..].copy_from_slice(&self.output.channels);
+        match self.master.write_all(&packet) {
+            Err(error) if error.kind() == io::ErrorKind::TimedOut => {
+                log::debug!("No one reads \"{}\", frame dropped", self.output.name);
+                Ok(())
+            },
+            result => Ok(result?),
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    fn is_sync(&self) -> bool {
+        self.output.is_sync()
+    }
+
+    fn set_sync(&mut self) {
+        self.output.set_sync();
+    }
+
+    fn set_async(&mut self) -> Result<(), OutputError> {
+        self.output.set_async()
+    }
+
+    fn set_packet_time(&mut self, _time: Duration) {}
+}
+
+#[derive(Debug)]
+pub enum OutputError {
+    Serial(serialport::Error),
+    Disconnected(DMXDisconnectionError),
+    Io(io::Error),
+    ///No device matches the selector (or the directory of a file or terminal is missing)
+    NotFound(String),
+    ///The output isn't available on this platform
+    #[cfg(not(unix))]
+    Unsupported,
+}
+
+impl From<serialport::Error> for OutputError {
+    fn from(error: serialport::Error) -> Self {
+        OutputError::Serial(error)
+    }
+}
+
+impl From<DMXDisconnectionError> for OutputError {
+    fn from(error: DMXDisconnectionError) -> Self {
+        OutputError::Disconnected(error)
+    }
+}
+
+impl From<io::Error> for OutputError {
+    fn from(error: io::Error) -> Self {
+        OutputError::Io(error)
+    }
+}
+
+impl Display for OutputError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputError::Serial(e) => write!(f, "{}", e),
+            OutputError::Disconnected(e) => write!(f, "{}", e),
+            OutputError::Io(e) => write!(f, "{}", e),
+            OutputError::NotFound(device) => write!(f, "Couldn't find device \"{}\"", device),
+            #[cfg(not(unix))]
+            OutputError::Unsupported => write!(f, "Not supported on this platform"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_output_only_appends_changed_frames() {
+        let path = std::env::temp_dir().join(format!("artnet_to_opendmx_output_{}", std::process::id()));
+        let name = path.to_str().unwrap();
+        let _ = std::fs::remove_file(name);
+        let mut output = MemoryOutput::new(name, Some(append(name).unwrap()));
+        output.update().unwrap();
+        output.update().unwrap();
+        output.set_channels([1; 512]);
+        output.update().unwrap();
+        output.update().unwrap();
+        assert_eq!(std::fs::read(name).unwrap(), [[0; 512], [1; 512]].concat());
+        std::fs::remove_file(name).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pty_output_only_removes_its_own_link() {
+        let directory = std::env::temp_dir();
+        let path = directory.join(format!("artnet_to_opendmx_pty_{}", std::process::id()));
+        let name = path.to_str().unwrap();
+        let _ = std::fs::remove_file(name);
+        // Links to anything but a terminal are kept
+        std::os::unix::fs::symlink(&directory, name).unwrap();
+        assert!(PtyOutput::open(name).is_err());
+        assert_eq!(std::fs::read_link(name).unwrap(), directory);
+        std::fs::remove_file(name).unwrap();
+
+        let output = PtyOutput::open(name).unwrap();
+        // Replaces the link of an earlier run
+        let replacing = PtyOutput::open(name).unwrap();
+        drop(output);
+        assert_eq!(std::fs::read_link(name).unwrap(), std::path::Path::new(&replacing.terminal));
+        drop(replacing);
+        assert!(std::fs::symlink_metadata(name).is_err());
+    }
+}
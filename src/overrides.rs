@@ -0,0 +1,47 @@
+//! The override layer the controls (HTTP API, OSC) put on top of the recieved frame
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Overrides {
+    pub blackout: bool,
+    ///Scales every channel, 1.0 is full
+    pub master: f32,
+    ///Channels held at a fixed value (0 based)
+    pub channels: [Option<u8>; 512],
+}
+
+impl Default for Overrides {
+    fn default() -> Self {
+        Self {
+            blackout: false,
+            master: 1.0,
+            channels: [None; 512],
+        }
+    }
+}
+
+impl Overrides {
+    pub fn apply(&self, frame: &[u8; 512]) -> [u8; 512] {
+        if self.blackout {
+            return [0; 512];
+        }
+        let mut output = *frame;
+        for (value, held) in output.iter_mut().zip(self.channels.iter()) {
+            if let Some(held) = held {
+                *value = *held;
+            }
+        }
+        if self.master < 1.0 {
+            output = output.map(|value| (value as f32 * self.master).round() as u8);
+        }
+        output
+    }
+
+    ///`true` if the output differs from the recieved frame
+    pub fn is_active(&self) -> bool {
+        self.blackout || self.master < 1.0 || self.channels.iter().any(Option::is_some)
+    }
+
+    pub fn held_channels(&self) -> usize {
+        self.channels.iter().filter(|held| held.is_some()).count()
+    }
+}
@@ -0,0 +1,192 @@
+//! Soft-patching of channels between the recieved frame and the dmx output
+use std::{fmt::{Display, Formatter}, ops::RangeInclusive, path::Path, str::FromStr};
+
+const CHANNELS: u16 = 512;
+
+///One rule per line like `101-120 -> 1-20` or `5 -> 6, 7-8`, unpatched channels pass through
+///and the last rule writing a channel wins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    rules: Vec<PatchRule>,
+    ///The input channel of every output channel (0 based)
+    table: Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PatchRule {
+    input: RangeInclusive<u16>,
+    outputs: Vec<RangeInclusive<u16>>,
+}
+
+impl Patch {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PatchError> {
+        let text = std::fs::read_to_string(path.as_ref()).map_err(|error| PatchError::Io(path.as_ref().display().to_string(), error))?;
+        text.parse()
+    }
+
+    pub fn apply(&self, input: &[u8; 512]) -> [u8; 512] {
+        let mut output = [0; 512];
+        output.iter_mut().zip(self.table.iter()).for_each(|(output, source)| *output = input[*source as usize]);
+        output
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl Default for Patch {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            table: (0..CHANNELS).collect(),
+        }
+    }
+}
+
+impl FromStr for Patch {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut patch = Patch::default();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| PatchError::Syntax(number + 1, message.into());
+            let (input, outputs) = line.split_once("->").ok_or_else(|| error("Expected <INPUT> -> <OUTPUTS>"))?;
+            let input = parse_range(input).map_err(|message| error(&message))?;
+            let mut rule = PatchRule { input, outputs: Vec::new() };
+            for output in outputs.split(',') {
+                let mut output = parse_range(output).map_err(|message| error(&message))?;
+                if rule.input.len() > 1 && output.len() == 1 {
+                    output = *output.start()..=*output.start() + (rule.input.len() as u16 - 1);
+                }
+                if rule.input.len() > 1 && output.len() != rule.input.len() {
+                    return Err(error("Output ranges must have the same length as the input range"));
+                }
+                if *output.end() > CHANNELS {
+                    return Err(error("Output range exceeds channel 512"));
+                }
+                for (i, channel) in output.clone().enumerate() {
+                    let source = match rule.input.len() {
+                        1 => *rule.input.start(),
+                        _ => rule.input.start() + i as u16,
+                    };
+                    patch.table[channel as usize - 1] = source - 1;
+                }
+                rule.outputs.push(output);
+            }
+            patch.rules.push(rule);
+        }
+        Ok(patch)
+    }
+}
+
+impl Display for Patch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for rule in &self.rules {
+            let outputs = rule.outputs.iter().map(format_range).collect::<Vec<_>>().join(", ");
+            writeln!(f, "{} -> {}", format_range(&rule.input), outputs)?;
+        }
+        Ok(())
+    }
+}
+
+///Parses a channel (e.g. `5`) or a range of channels (e.g. `1-16`)
+pub fn parse_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    let channel = |s: &str| match s.trim().parse::<u16>() {
+        Ok(channel @ 1..=CHANNELS) => Ok(channel),
+        _ => Err(format!("Invalid channel \"{}\" (1-512)", s.trim())),
+    };
+    match s.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (channel(start)?, channel(end)?);
+            if start > end {
+                return Err(format!("Invalid range \"{}\"", s.trim()));
+            }
+            Ok(start..=end)
+        },
+        None => {
+            let channel = channel(s)?;
+            Ok(channel..=channel)
+        },
+    }
+}
+
+fn format_range(range: &RangeInclusive<u16>) -> String {
+    match range.start() == range.end() {
+        true => range.start().to_string(),
+        false => format!("{}-{}", range.start(), range.end()),
+    }
+}
+
+#[derive(Debug)]
+pub enum PatchError {
+    Io(String, std::io::Error),
+    Syntax(usize, String),
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchError::Io(path, e) => write!(f, "Couldn't read patch file \"{}\": {}", path, e),
+            PatchError::Syntax(line, message) => write!(f, "Invalid patch in line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> [u8; 512] {
+        std::array::from_fn(|index| (index % 256) as u8)
+    }
+
+    #[test]
+    fn moves_ranges_and_copies_channels() {
+        let patch = "# comment\n101-120 -> 1-20\n5 -> 46, 47-48 # copies\n".parse::<Patch>().unwrap();
+        let output = patch.apply(&frame());
+        assert_eq!(output[..20], frame()[100..120]);
+        assert_eq!(output[45..48], [4, 4, 4]);
+        // Unpatched channels pass through
+        assert_eq!(output[20], 20);
+        assert_eq!(output[48], 48);
+        assert_eq!(output[511], 255);
+    }
+
+    #[test]
+    fn expands_the_first_channel_of_an_output_range() {
+        let patch = "1-3 -> 10".parse::<Patch>().unwrap();
+        assert_eq!(patch.apply(&frame())[9..12], [0, 1, 2]);
+        assert_eq!(patch.to_string(), "1-3 -> 10-12\n");
+    }
+
+    #[test]
+    fn last_rule_wins() {
+        let patch = "1 -> 10\n2 -> 10".parse::<Patch>().unwrap();
+        assert_eq!(patch.apply(&frame())[9], 1);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for (text, line) in [("1 -> 2\n1 > 2", 2), ("0 -> 1", 1), ("1-4 -> 1-2", 1), ("1-4 -> 510", 1), ("4-1 -> 1", 1), ("513 -> 1", 1)] {
+            match text.parse::<Patch>() {
+                Err(PatchError::Syntax(error_line, _)) => assert_eq!(error_line, line, "{text}"),
+                result => panic!("{text} parsed to {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn parses_channels_and_ranges() {
+        assert_eq!(parse_range(" 7 "), Ok(7..=7));
+        assert_eq!(parse_range("1-512"), Ok(1..=512));
+        assert!(parse_range("").is_err());
+        assert!(parse_range("5-a").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Playback of show files as an additional dmx source of the runner
+use std::{collections::HashMap, fmt::{Display, Formatter}, io, net::{Ipv4Addr, SocketAddr, SocketAddrV4}, sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc, Mutex}, thread::{self, JoinHandle}, time::{Duration, Instant}};
+
+use chrono::Local;
+use log::{info, error};
+
+use crate::cli::Playback;
+use crate::runner::{InputEvent, InputFrame, Protocol, LISTENER_TIMEOUT};
+use crate::show::{ShowFrame, ShowReader};
+
+///The sender of all played back frames
+pub const PLAYBACK_SENDER: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+///How often the look is sent again while paused or finished, well within the signal timeout of the runner
+const HOLD_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackState {
+    Playing,
+    Paused,
+    ///Outside of the scheduled time windows
+    Scheduled,
+    Finished,
+}
+
+impl Display for PlaybackState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlaybackState::Playing => write!(f, "Playing"),
+            PlaybackState::Paused => write!(f, "Paused"),
+            PlaybackState::Scheduled => write!(f, "Scheduled"),
+            PlaybackState::Finished => write!(f, "Finished"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackStatus {
+    pub state: PlaybackState,
+    ///The current time in the show
+    pub position: Duration,
+    pub length: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackCommand {
+    Play,
+    Pause,
+    ///Jumps back to the start offset
+    Restart,
+}
+
+///Controls the playback thread, which stops with the runner
+pub struct Player {
+    commands: mpsc::Sender<PlaybackCommand>,
+    status: Arc<Mutex<PlaybackStatus>>,
+    thread: JoinHandle<()>,
+}
+
+impl Player {
+    pub fn send(&self, command: PlaybackCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn status(&self) -> Option<PlaybackStatus> {
+        self.status.lock().ok().map(|status| *status)
+    }
+
+    pub fn join(self) {
+        drop(self.commands);
+        if self.thread.join().is_err() {
+            error!("Playback thread panicked");
+        }
+    }
+}
+
+struct PlaybackThread {
+    reader: ShowReader,
+    playback: Playback,
+    tx: mpsc::Sender<InputEvent>,
+    status: Arc<Mutex<PlaybackStatus>>,
+    state: PlaybackState,
+    position: Duration,
+    ///The next frame to send once `position` reaches it
+    next: Option<ShowFrame>,
+    ///When `position` was last advanced
+    last: Instant,
+    ///The last sent frame of every universe
+    look: HashMap<u16, [u8; 512]>,
+    last_sent: Instant,
+}
+
+impl PlaybackThread {
+    ///Jumps to the start offset and sends the look of the show at that time
+    fn seek_offset(&mut self) -> io::Result<()> {
+        self.reader.rewind()?;
+        self.position = self.playback.offset;
+        let mut frames = HashMap::new();
+        self.next = None;
+        while let Some(frame) = self.reader.next_frame()? {
+            if frame.time >= self.playback.offset {
+                self.next = Some(frame);
+                break;
+            }
+            frames.insert(frame.universe, frame.channels);
+        }
+        for (universe, channels) in frames {
+            self.send(universe, channels);
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, universe: u16, channels: [u8; 512]) {
+        self.look.insert(universe, channels);
+        self.last_sent = Instant::now();
+        let _ = self.tx.send(InputEvent::Dmx(Box::new(InputFrame {
+            sender: PLAYBACK_SENDER,
+            universe,
+            channels,
+            protocol: Protocol::Playback,
+        })));
+    }
+
+    ///Sends every frame up to the current position. Returns the time until the next frame is due.
+    fn play(&mut self) -> io::Result<Option<Duration>> {
+        loop {
+            match self.next.take() {
+                Some(frame) if frame.time <= self.position => self.send(frame.universe, frame.channels),
+                Some(frame) => {
+                    let due = (frame.time - self.position).div_f32(self.playback.speed);
+                    self.next = Some(frame);
+                    return Ok(Some(due));
+                },
+                None if self.playback.looping => {
+                    self.seek_offset()?;
+                    // An empty show would loop forever
+                    if self.next.is_none() {
+                        return Ok(None);
+                    }
+                    continue;
+                },
+                None => return Ok(None),
+            }
+            self.next = self.reader.next_frame()?;
+        }
+    }
+
+    ///Sends the current look again, so the runner doesn't consider the signal lost
+    fn hold(&mut self) {
+        let look = self.look.clone();
+        for (universe, channels) in look {
+            self.send(universe, channels);
+        }
+    }
+
+    fn in_schedule(&self) -> bool {
+        let now = Local::now().time();
+        self.playback.schedule.is_empty() || self.playback.schedule.iter().any(|window| window.contains(now))
+    }
+
+    fn set_state(&mut self, state: PlaybackState) {
+        if self.state != state {
+            info!("Playback {}", state.to_string().to_lowercase());
+            // Paused time doesn't count
+            self.last = Instant::now();
+        }
+        self.state = state;
+        if let Ok(mut status) = self.status.lock() {
+            status.state = state;
+            status.position = self.position;
+        }
+    }
+
+    fn run(mut self, running: Arc<AtomicBool>, commands: mpsc::Receiver<PlaybackCommand>) -> io::Result<()> {
+        self.seek_offset()?;
+        while running.load(Ordering::Relaxed) {
+            let mut timeout = LISTENER_TIMEOUT;
+            match (self.state, self.in_schedule()) {
+                (PlaybackState::Playing, false) => self.set_state(PlaybackState::Scheduled),
+                (PlaybackState::Scheduled, true) => self.set_state(PlaybackState::Playing),
+                (PlaybackState::Playing, true) => {
+                    self.position += self.last.elapsed().mul_f32(self.playback.speed);
+                    self.last = Instant::now();
+                    match self.play()? {
+                        Some(due) => {
+                            timeout = timeout.min(due);
+                            self.set_state(PlaybackState::Playing);
+                        },
+                        None => {
+                            self.position = self.position.min(self.reader.length());
+                            self.set_state(PlaybackState::Finished);
+                        },
+                    }
+                },
+                (PlaybackState::Paused | PlaybackState::Finished, _) => {
+                    if self.last_sent.elapsed() >= HOLD_INTERVAL {
+                        self.hold();
+                    }
+                    timeout = timeout.min(HOLD_INTERVAL);
+                },
+                (PlaybackState::Scheduled, false) => {},
+            }
+            match commands.recv_timeout(timeout) {
+                Ok(PlaybackCommand::Play) => match self.state {
+                    PlaybackState::Paused => self.set_state(PlaybackState::Playing),
+                    PlaybackState::Finished => {
+                        self.seek_offset()?;
+                        self.set_state(PlaybackState::Playing);
+                    },
+                    _ => {},
+                },
+                Ok(PlaybackCommand::Pause) => if self.state == PlaybackState::Playing {
+                    self.set_state(PlaybackState::Paused);
+                },
+                Ok(PlaybackCommand::Restart) => {
+                    self.seek_offset()?;
+                    if self.state == PlaybackState::Finished {
+                        self.set_state(PlaybackState::Playing);
+                    } else {
+                        self.set_state(self.state);
+                    }
+                },
+                Err(mpsc::RecvTimeoutError::Timeout) => {},
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+///Starts playing `reader` into the runner input, until `running` is cleared
+pub fn start(reader: ShowReader, playback: Playback, tx: mpsc::Sender<InputEvent>, running: Arc<AtomicBool>) -> Player {
+    let status = Arc::new(Mutex::new(PlaybackStatus {
+        state: PlaybackState::Playing,
+        position: playback.offset,
+        length: reader.length(),
+    }));
+    let (commands, commands_rx) = mpsc::channel();
+    let playback_thread = PlaybackThread {
+        reader,
+        playback,
+        tx,
+        status: status.clone(),
+        state: PlaybackState::Playing,
+        position: Duration::ZERO,
+        next: None,
+        last: Instant::now(),
+        look: HashMap::new(),
+        last_sent: Instant::now(),
+    };
+    let thread_status = status.clone();
+    let thread = thread::spawn(move || {
+        if let Err(error) = playback_thread.run(running, commands_rx) {
+            error!("Couldn't read show file: {}. Stopping playback", error);
+            if let Ok(mut status) = thread_status.lock() {
+                status.state = PlaybackState::Finished;
+            }
+        }
+    });
+    Player {
+        commands,
+        status,
+        thread,
+    }
+}
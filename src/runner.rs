@@ -1,180 +1,736 @@
-use crate::cli::Arguments;
+use crate::artnet::{self, AddressCommand, ArtAddress, NodeInfo, PortInfo, Responder, SharedNodeInfo};
+use crate::cli::{Arguments, LossPolicy, MergeMode, Options, RecordArguments};
+use crate::device::{self, DeviceSelector};
+use crate::filter::SenderFilter;
+use crate::merge::Merger;
+use crate::network::{self, NetworkInterface};
+use crate::osc;
+use crate::output::OutputError;
+use crate::overrides::Overrides;
+use crate::patch::Patch;
+use crate::playback::{self, PlaybackCommand, PlaybackStatus};
+use crate::sacn::{self, SacnGroups};
+use crate::scene::{self, Scene};
+use crate::show::{ShowReader, ShowWriter};
+use crate::stats::{OutputCounters, Statistics, StatisticsCollector};
+use crate::supervisor::{DeviceState, DeviceSupervisor};
 
-use std::{fmt::{Display, Formatter}, net::SocketAddr, sync::mpsc};
+use std::{fmt::{Display, Formatter}, collections::HashMap, net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4}, path::PathBuf, sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc, Mutex}, thread::{self, JoinHandle}, time::{Duration, Instant}};
 
-use artnet_protocol::{PortAddress, PollReply};
-use artnet_reciever::ArtnetRecieverBuilder;
-use open_dmx::DMXSerial;
-use serialport::available_ports;
+use artnet_protocol::PortAddress;
 use log::{info, debug, warn, error};
 
 
 pub type RunnerUpdateReciever = mpsc::Receiver<RunnerUpdate>;
 
-#[derive(Default, Debug, Clone, Copy)] //all false
+///How long the network listeners block on their socket before checking whether the runner stopped
+pub const LISTENER_TIMEOUT: Duration = Duration::from_millis(100);
+///How long a listener waits after its socket failed, so a lasting error (e.g. a removed interface) doesn't spin
+const LISTENER_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+///How often the listeners report the packets of rejected senders
+const REJECTION_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+///Without an ArtSync for this long the runner falls back to immediate output
+const SYNC_TIMEOUT: Duration = Duration::from_secs(4);
+///Without a valid frame for this long the signal of a route counts as lost
+const SIGNAL_TIMEOUT: Duration = Duration::from_secs(4);
+///How often the runner checks timeouts and drives fades while no packets arrive
+const TICK_INTERVAL: Duration = Duration::from_millis(25);
+///The minimum time between two [`RunnerUpdate`]s
+pub const UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+///Synchronous interfaces repeat their last frame this often while the signal is active
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+///Handles a failed recieve of a listener. Timeouts only let it check whether the runner stopped,
+///other errors get logged and waited out.
+pub fn listener_error(name: &str, error: std::io::Error) {
+    if !matches!(error.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) {
+        warn!("{} reciever failed: {}", name, error);
+        thread::sleep(LISTENER_ERROR_BACKOFF);
+    }
+}
+
+///Counts the packets of rejected senders in a listener, so a flooding sender doesn't flood the runner too
+pub struct Rejections {
+    protocol: Protocol,
+    packets: HashMap<IpAddr, u64>,
+    last_report: Instant,
+}
+
+impl Rejections {
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            packets: HashMap::new(),
+            last_report: Instant::now(),
+        }
+    }
+
+    pub fn count(&mut self, sender: IpAddr) {
+        *self.packets.entry(sender).or_default() += 1;
+    }
+
+    ///Sends the counted packets to the runner once every [`REJECTION_REPORT_INTERVAL`], one event per sender
+    pub fn report(&mut self, tx: &mpsc::Sender<InputEvent>) -> Result<(), mpsc::SendError<InputEvent>> {
+        if self.last_report.elapsed() < REJECTION_REPORT_INTERVAL {
+            return Ok(());
+        }
+        self.last_report = Instant::now();
+        for (sender, packets) in self.packets.drain() {
+            tx.send(InputEvent::Rejected(sender, self.protocol, packets))?;
+        }
+        Ok(())
+    }
+}
+
+///Controls a running bridge
+///
+///Dropping the handle stops the runner and waits for it.
+pub struct RunnerHandle {
+    updates: RunnerUpdateReciever,
+    commands: mpsc::Sender<InputEvent>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RunnerHandle {
+    pub fn updates(&self) -> &RunnerUpdateReciever {
+        &self.updates
+    }
+
+    pub fn send(&self, command: RunnerCommand) {
+        if self.commands.send(InputEvent::Command(command)).is_err() {
+            warn!("Runner stopped, command ignored");
+        }
+    }
+
+    ///Tells the runner to stop, [`RunnerHandle::join`] waits until it did
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|thread| !thread.is_finished())
+    }
+
+    ///Waits until the runner stopped and released its devices and sockets
+    pub fn join(mut self) {
+        self.join_thread();
+    }
+
+    fn join_thread(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Runner thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for RunnerHandle {
+    fn drop(&mut self) {
+        self.stop();
+        self.join_thread();
+    }
+}
+
+///The state of the runner, sent whenever it changes (at most every [`UPDATE_INTERVAL`])
+#[derive(Default, Debug, Clone, PartialEq)] //all false
 pub struct RunnerUpdate {
+    pub connected_to_artnet: bool,
+    ///Output is only committed on ArtSync
+    pub synchronous: bool,
+    ///One entry for every configured route, in the same order as [`Arguments::routes`]
+    pub routes: Vec<RouteUpdate>,
+    ///The configuration after a controller changed it via ArtAddress
+    ///
+    ///Kept in every update until one got delivered.
+    pub reconfigured: Option<Arguments>,
+    pub statistics: Statistics,
+    ///The output is being recorded to a show file
+    pub recording: bool,
+    ///The state of the show file playback, if configured
+    pub playback: Option<PlaybackStatus>,
+    ///All outputs are forced to black
+    pub blackout: bool,
+    ///Scales all outputs, 1.0 is full
+    pub master: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RouteUpdate {
+    pub universe: u16,
+    ///The last sender since the previous update
     pub dmx_recieved: Option<SocketAddr>,
+    ///The latest sender and when its last frame arrived, kept between updates
+    pub last_packet: Option<(Instant, SocketAddr)>,
+    ///Output was sent since the previous update
     pub dmx_sent: bool,
-    
-    pub connected_to_artnet: bool,
-    pub connected_to_dmx: bool,
+    ///The number of senders currently merged into this route
+    pub sources: usize,
+    ///No valid frame arrived for [`SIGNAL_TIMEOUT`]
+    pub signal_lost: bool,
+
+    pub device: DeviceState,
+    ///The channels currently sent to the device
+    pub channels: [u8; 512],
+    ///The number of channels held by the controls
+    pub held_channels: usize,
 }
 
-pub fn create_runner(arguments: Arguments) -> Result<RunnerUpdateReciever, RunnerCreationError> {
-    let (tx, rx) = mpsc::sync_channel(1);
-    
-    info!("Checking for device named \"{}\"...", arguments.device_name);
-    let ports= match available_ports() {
-        Err(error) => {
-            error!("Coulnd't get available ports list: {}", error);
-            return Err(RunnerCreationError::PortListingError(error));
-        },
-        Ok(ports) => ports,
-    };
-    let mut device = "";
-    for port in ports {
-        if port.port_name == arguments.device_name {
-            info!("Found device \"{}\"", arguments.device_name);
-            device = &arguments.device_name;
-            break;
+impl Default for RouteUpdate {
+    fn default() -> Self {
+        Self {
+            universe: 0,
+            dmx_recieved: None,
+            last_packet: None,
+            dmx_sent: false,
+            sources: 0,
+            signal_lost: false,
+            device: DeviceState::default(),
+            channels: [0; 512],
+            held_channels: 0,
+        }
+    }
+}
+
+///Everything the network listeners forward to the runner
+pub enum InputEvent {
+    Dmx(Box<InputFrame>),
+    ///An ArtSync packet
+    Sync(SocketAddr),
+    Address(SocketAddr, Box<ArtAddress>),
+    ///Packets from a sender which isn't an allowed controller
+    Rejected(IpAddr, Protocol, u64),
+    Command(RunnerCommand),
+}
+
+///Commands from the controls (e.g. the GUI) to a running runner
+#[derive(Debug, Clone)]
+pub enum RunnerCommand {
+    StartRecording(PathBuf),
+    StopRecording,
+    Playback(PlaybackCommand),
+    ///Forces all outputs to black while enabled
+    Blackout(bool),
+    ///Scales all outputs, 1.0 is full
+    Master(f32),
+    ///Holds a channel (1-512) of the route of `universe`, of all routes if it's `None`. A `None` value releases it.
+    Channel {
+        universe: Option<u16>,
+        channel: u16,
+        value: Option<u8>,
+    },
+    ///Releases all held channels
+    ReleaseChannels,
+}
+
+///The show file all routes record to, if recording
+type SharedRecorder = Arc<Mutex<Option<ShowWriter>>>;
+
+///A dmx frame recieved by one of the network listeners
+pub struct InputFrame {
+    pub sender: SocketAddr,
+    ///The universe in art-net numbering
+    pub universe: u16,
+    pub channels: [u8; 512],
+    pub protocol: Protocol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Artnet,
+    Sacn,
+    ///A show file played back by the runner
+    Playback,
+    ///Commands only, counted when they get rejected
+    Osc,
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::Artnet => write!(f, "Art-Net"),
+            Protocol::Sacn => write!(f, "sACN"),
+            Protocol::Playback => write!(f, "Playback"),
+            Protocol::Osc => write!(f, "OSC"),
         }
     }
-    if device.is_empty() {
-        error!("Couldn't find device named \"{}\"", arguments.device_name);
-        return Err(RunnerCreationError::LocateDeviceError);
+}
+
+///The state of the incoming signal of a route
+enum Signal {
+    ///No frame recieved yet
+    Waiting,
+    Active(Instant),
+    ///Holds the output at the moment the signal got lost
+    Lost {
+        since: Instant,
+        look: Box<[u8; 512]>,
+    },
+}
+
+///A route with its opened dmx interface
+struct RouteOutput {
+    universe: PortAddress,
+    ///The universe the route started with, ArtAddress can reset to it
+    initial_universe: u16,
+    device: DeviceSupervisor,
+    ///The latest frame, before the overrides
+    frame: [u8; 512],
+    overrides: Overrides,
+    merger: Merger,
+    ///A frame waiting for the next ArtSync of its sender
+    pending: Option<(IpAddr, [u8; 512])>,
+    patch: Option<Patch>,
+    signal: Signal,
+    last_write: Instant,
+    counters: OutputCounters,
+    recorder: SharedRecorder,
+    loss: LossPolicy,
+    failsafe_scene: Option<Scene>,
+}
+
+impl RouteOutput {
+    fn good_output(&self) -> u8 {
+        let mut good_output = artnet::GOOD_OUTPUT_DATA;
+        if self.merger.is_merging() {
+            good_output |= artnet::GOOD_OUTPUT_MERGING;
+        }
+        if self.merger.mode() == MergeMode::Ltp {
+            good_output |= artnet::GOOD_OUTPUT_LTP;
+        }
+        good_output
     }
-    info!("Starting dmx interface...");
-    let mut dmx = match DMXSerial::open_sync(device) {
-        Ok(dmx) => dmx,
-        Err(error) => {
-            error!("Couldn't open dmx interface: {}", error);
-            return Err(RunnerCreationError::DeviceOpeningError(error));
-        },
-    };
-    if let Some(time) = arguments.options.break_time {
-        debug!("Setting dmx interface break time to {}ms", time.as_millis());
-        dmx.set_packet_time(time);
+
+    ///Records, patches and sends a recieved frame
+    ///
+    ///The merged input gets recorded, so a show plays back through the patch like live input and
+    ///the blackout, overrides and loss policy of the recording don't end up in it.
+    fn send(&mut self, channels: [u8; 512], route_update: &mut RouteUpdate) {
+        if let Ok(mut recorder) = self.recorder.lock() {
+            if let Some(Err(error)) = recorder.as_mut().map(|writer| writer.write_frame(self.universe.into(), &channels)) {
+                error!("Couldn't record frame: {}. Stopping recording", error);
+                *recorder = None;
+            }
+        }
+        let channels = match &self.patch {
+            Some(patch) => patch.apply(&channels),
+            None => channels,
+        };
+        self.write(channels, route_update);
     }
-    if arguments.options.remember {
-        debug!("Setting dmx interface to remember mode");
-        dmx.set_async();
-        dmx.set_channels([0; 512]);
-        if let Err(error) = dmx.update_async() {
-            return Err(RunnerCreationError::DeviceUpdateError(error));
+
+    fn write(&mut self, channels: [u8; 512], route_update: &mut RouteUpdate) {
+        self.frame = channels;
+        let channels = self.overrides.apply(&channels);
+        route_update.channels = channels;
+        self.last_write = Instant::now();
+        self.counters.frames += 1;
+        match self.device.write(channels) {
+            Ok(sent) => {
+                route_update.dmx_sent = sent;
+                if sent {
+                    debug!("Updated dmx channels on interface \"{}\"", self.device.name());
+                }
+            },
+            Err(error) => {
+                self.counters.serial_failures += 1;
+                error!("Couldn't update dmx channels on interface \"{}\": {}", self.device.name(), error);
+                route_update.dmx_sent = false;
+            },
         }
     }
-    info!("Started!");
 
+    ///Repeats the last frame on synchronous interfaces, asynchronous ones refresh on their own
+    fn refresh(&mut self, route_update: &mut RouteUpdate) {
+        let active = matches!(self.signal, Signal::Active(_)) || self.overrides.is_active();
+        if self.device.is_sync() && active && self.last_write.elapsed() >= REFRESH_INTERVAL {
+            self.write(self.frame, route_update);
+        }
+    }
 
-    info!("Starting art-net listener...");
+    ///Changes the override layer and sends the frame again if that changed it
+    fn update_overrides(&mut self, change: impl FnOnce(&mut Overrides), route_update: &mut RouteUpdate) {
+        let overrides = self.overrides;
+        change(&mut self.overrides);
+        route_update.held_channels = self.overrides.held_channels();
+        if self.overrides != overrides {
+            self.write(self.frame, route_update);
+        }
+    }
 
-    debug!("Creating art-net poll reply packet...");
-    let output = match arguments.options.controller.is_some() {
-        true => 0x8A,
-        false => 0x80,
-    };
-    let mut short_name = [0; 18];
-    "artnet2opendmx".bytes().enumerate().for_each(|(i, b)| short_name[i] = b);
-    let mut long_name = [0; 64];
-    match &arguments.options.name {
-        Some(name) if name.as_bytes().len() <= 64 => name.clone(),
-        _ => "artnet_to_opendmx_node".into(),
-    }.as_bytes().iter().zip(long_name.iter_mut()).for_each(|(a, b)| *b = *a);
-
-    let poll_reply = PollReply {
-        address: [0, 0, 0, 0].into(),
-        port: 0,
-        version: [1, 0],
-        port_address: arguments.universe.to_be_bytes(),
-        oem: [0; 2],
-        ubea_version: 0,
-        status_1: 0,
-        esta_code: 0,
-        short_name,
-        long_name,
-        node_report: [0; 64],
-        num_ports: [0, 1],
-        port_types: [0x40, 0, 0, 0],
-        good_input: [8; 4],
-        good_output: [output, 0, 0, 0],
-        swin: [0; 4],
-        swout: [0; 4],
-        sw_video: 0,
-        sw_macro: 0,
-        sw_remote: 0,
-        style: 0x00,
-        mac: [0; 6],
-        bind_ip: [0, 0, 0, 0].into(),
-        bind_index: 1,
-        status_2: 0,
-        filler: [0; 26],
-        spare: [0; 3],
-    };
+    ///Reconnects a missing device
+    fn supervise(&mut self, route_update: &mut RouteUpdate) {
+        let state = self.device.state();
+        if self.device.poll() && state == DeviceState::Lost {
+            self.counters.reconnects += 1;
+        }
+        route_update.device = self.device.state();
+    }
 
-    debug!("Creating art-net reciever...");
+    fn signal_recieved(&mut self, route_update: &mut RouteUpdate) {
+        if let Signal::Lost { .. } = self.signal {
+            info!("Signal on universe {} restored", route_update.universe);
+        }
+        self.signal = Signal::Active(Instant::now());
+        route_update.signal_lost = false;
+    }
+
+    ///Detects signal loss and applies the loss policy
+    fn check_signal(&mut self, route_update: &mut RouteUpdate) {
+        let (since, look) = match &self.signal {
+            Signal::Waiting => return,
+            &Signal::Active(last_frame) => {
+                if last_frame.elapsed() < SIGNAL_TIMEOUT {
+                    return;
+                }
+                warn!("Lost signal on universe {} ({})", route_update.universe, self.loss);
+                route_update.signal_lost = true;
+                let look = Box::new(self.frame);
+                self.signal = Signal::Lost { since: Instant::now(), look };
+                if let Some(scene) = self.failsafe_scene {
+                    self.write(scene, route_update);
+                }
+                return;
+            },
+            Signal::Lost { since, look } => (*since, **look),
+        };
+        let channels = match self.loss {
+            LossPolicy::Blackout(time) if since.elapsed() >= time => [0; 512],
+            LossPolicy::Fade(time) => {
+                let level = 1.0 - (since.elapsed().as_secs_f32() / time.as_secs_f32().max(f32::EPSILON)).min(1.0);
+                look.map(|value| (value as f32 * level).round() as u8)
+            },
+            _ => return,
+        };
+        if channels != self.frame {
+            self.write(channels, route_update);
+        }
+    }
+}
 
-    let artnet_reciever_template = ArtnetRecieverBuilder::default()
-        .socket_address(format!("{}:{}", arguments.options.controller.clone().unwrap_or("0.0.0.0".into()), arguments.options.port.unwrap_or(6454)).parse().unwrap()) //Port must be valid if the value is 16 bit
-        .poll_reply(poll_reply);
+pub fn create_runner(mut arguments: Arguments) -> Result<RunnerHandle, RunnerCreationError> {
+    let (tx, rx) = mpsc::sync_channel(1);
 
-    let artnet_output = match artnet_reciever_template.build() {
-        Ok(reciever) => reciever,
-        Err(error) => {
-            error!("Couldn't create art-net reciever: {}", error);
-            return Err(RunnerCreationError::ArtnetCreationError(error));
+    if arguments.routes.is_empty() {
+        error!("No routes configured");
+        return Err(RunnerCreationError::NoRoutesError);
+    }
+
+    let failsafe_scene = match &arguments.options.loss {
+        LossPolicy::Scene(path) => match scene::load(path) {
+            Ok(scene) => Some(scene),
+            Err(error) => {
+                error!("{}", error);
+                return Err(RunnerCreationError::SceneLoadingError(error));
+            },
+        },
+        _ => None,
+    };
+    let recorder = match &arguments.options.record {
+        Some(path) => match ShowWriter::create(path) {
+            Ok(writer) => {
+                info!("Recording to \"{}\"", path.display());
+                Some(writer)
+            },
+            Err(error) => {
+                error!("Couldn't create show file: {}", error);
+                return Err(RunnerCreationError::RecordingError(error));
+            },
         },
+        None => None,
     };
+    let recorder: SharedRecorder = Arc::new(Mutex::new(recorder));
+    let show = match &arguments.options.playback {
+        Some(playback) => match ShowReader::open(&playback.path) {
+            Ok(reader) => Some((reader, playback.clone())),
+            Err(error) => {
+                error!("Couldn't open show file \"{}\": {}", playback.path.display(), error);
+                return Err(RunnerCreationError::PlaybackError(error));
+            },
+        },
+        None => None,
+    };
+    let mut selectors = Vec::with_capacity(arguments.routes.len());
+    for route in &arguments.routes {
+        selectors.push(match route.output.unwrap_or(arguments.options.output).is_device() {
+            true => match DeviceSelector::parse(&route.device_name) {
+                Ok(selector) => Some(selector),
+                Err(error) => return Err(RunnerCreationError::InvalidDeviceError(error)),
+            },
+            false => None,
+        });
+    }
+    // Different names may still select the same device, e.g. its USB ids and its port name
+    let devices = selectors.iter().flatten().collect::<Vec<_>>();
+    if devices.len() > 1 {
+        if let Some((first, second, port)) = serialport::available_ports().ok().and_then(|ports| device::find_clash(&devices, &ports)) {
+            return Err(RunnerCreationError::InvalidDeviceError(format!("Devices \"{}\" and \"{}\" are both \"{}\"", first, second, port)));
+        }
+    }
+    let mut outputs = Vec::with_capacity(arguments.routes.len());
+    for (route, selector) in arguments.routes.iter().zip(selectors) {
+        let kind = route.output.unwrap_or(arguments.options.output);
+        info!("Starting {} output for universe {}...", kind, route.universe);
+        let mut device = DeviceSupervisor::new(kind, &route.device_name, selector, arguments.options.break_time, arguments.options.loss.refreshes_output());
+        match device.connect() {
+            Ok(()) => info!("Opened device \"{}\"", device.name()),
+            // Devices which aren't plugged in yet are picked up once they are
+            Err(OutputError::NotFound(_)) => warn!("Couldn't find device named \"{}\", waiting for it...", route.device_name),
+            Err(error) => {
+                error!("Couldn't open dmx interface: {}", error);
+                return Err(RunnerCreationError::DeviceOpeningError(error));
+            },
+        }
+        let universe = match PortAddress::try_from(route.universe) {
+            Ok(universe) => universe,
+            Err(_) => return Err(RunnerCreationError::InvalidUniverseError(route.universe)),
+        };
+        outputs.push(RouteOutput {
+            universe,
+            initial_universe: route.universe,
+            device,
+            frame: [0; 512],
+            overrides: Overrides::default(),
+            merger: Merger::new(arguments.options.merge),
+            pending: None,
+            patch: arguments.options.patch.clone().filter(|patch| !patch.is_empty()),
+            signal: Signal::Waiting,
+            last_write: Instant::now(),
+            counters: OutputCounters::default(),
+            recorder: recorder.clone(),
+            loss: arguments.options.loss.clone(),
+            failsafe_scene,
+        });
+    }
+    info!("Started!");
 
-    info! ("Started!");
-    std::thread::spawn(move || {
-        let mut update = RunnerUpdate::default();
-        loop {
-            update.dmx_sent = false;
-            update.dmx_recieved = None;
 
-            if dmx.is_async() {
-                update.dmx_sent = true;
-            }
+    let (input_tx, input_rx) = mpsc::channel();
 
-            match artnet_output.try_recv() {
-                Ok((sender, output)) => {
-                    update.connected_to_artnet = true;
-                    if output.port_address == PortAddress::try_from(arguments.universe).unwrap() {
-                        update.dmx_recieved = Some(sender);
-                        debug!("Received output for universe {} from {}", arguments.universe, sender);
-                        let mut channels = [0; 512];
-                        output.to_bytes().unwrap()[8..].iter().zip(channels.iter_mut()).for_each(|(a, b)| *b = *a);
-                        dmx.set_channels(channels);
-                        update.dmx_sent = true;
-                        match dmx.update() {
-                            Ok(_) => {
-                                update.dmx_sent = true;
-                            },
-                            Err(_) => {
-                                error!("Couldn't update dmx channels. Interface got disconnected.");
-                                debug!("Trying to reconnect...");
-                                if let Err(e) = dmx.reopen() {
-                                    error!("Couldn't reconnect to dmx interface: {}", e);
-                                    update.dmx_sent = false;
-                                }
+    let node = Arc::new(Mutex::new(NodeInfo {
+        short_name: match &arguments.options.short_name {
+            Some(name) if name.len() <= 17 => name.clone(),
+            _ => "artnet2opendmx".into(),
+        },
+        long_name: match &arguments.options.name {
+            Some(name) if name.len() <= 64 => name.clone(),
+            _ => "artnet_to_opendmx_node".into(),
+        },
+        ports: outputs.iter().map(|route| PortInfo {
+            universe: route.universe.into(),
+            good_output: route.good_output(),
+        }).collect(),
+    }));
+    let running = Arc::new(AtomicBool::new(true));
+    let universes = arguments.routes.iter().map(|route| route.universe).collect::<Vec<_>>();
+    let Listeners { responder, sacn, threads: listeners } = start_listeners(&arguments.options, &universes, node.clone(), input_tx.clone(), running.clone())?;
+    let player = show.map(|(reader, playback)| {
+        info!("Playing \"{}\" ({}s)", playback.path.display(), reader.length().as_secs());
+        playback::start(reader, playback, input_tx.clone(), running.clone())
+    });
+
+    let thread_running = running.clone();
+    let thread = thread::spawn(move || {
+        let running = thread_running;
+        let mut last_sync: Option<Instant> = None;
+        let mut update = RunnerUpdate {
+            connected_to_artnet: true,
+            synchronous: false,
+            routes: arguments.routes.iter().map(|route| RouteUpdate {
+                universe: route.universe,
+                ..Default::default()
+            }).collect(),
+            reconfigured: None,
+            statistics: Statistics::default(),
+            recording: false,
+            playback: None,
+            blackout: false,
+            master: 1.0,
+        };
+        let mut statistics = StatisticsCollector::new();
+        let mut last_update: Option<(Instant, RunnerUpdate)> = None;
+        let mut next_tick = Instant::now();
+        while running.load(Ordering::Relaxed) {
+            match input_rx.recv_timeout(next_tick.saturating_duration_since(Instant::now())) {
+                Ok(InputEvent::Dmx(frame)) => {
+                    let InputFrame { sender, universe, channels, protocol } = *frame;
+                    statistics.packet(sender.ip(), protocol, outputs.iter().any(|route| universe == u16::from(route.universe)));
+                    for (route_update, route) in update.routes.iter_mut().zip(outputs.iter_mut()) {
+                        if universe != u16::from(route.universe) {
+                            continue;
+                        }
+                        route_update.dmx_recieved = Some(sender);
+                        route_update.last_packet = Some((Instant::now(), sender));
+                        debug!("Received output for universe {} from {}", route_update.universe, sender);
+                        route.signal_recieved(route_update);
+                        let channels = route.merger.merge(sender, channels);
+                        // Merged output ignores ArtSync
+                        if update.synchronous && protocol == Protocol::Artnet && !route.merger.is_merging() {
+                            route.pending = Some((sender.ip(), channels));
+                        } else {
+                            route.send(channels, route_update);
+                        }
+                    }
+                },
+                Ok(InputEvent::Sync(sender)) => {
+                    if !update.synchronous {
+                        info!("Recieved ArtSync from {}. Switching to synchronous output", sender);
+                        update.synchronous = true;
+                    }
+                    last_sync = Some(Instant::now());
+                    for (route_update, route) in update.routes.iter_mut().zip(outputs.iter_mut()) {
+                        match route.pending {
+                            Some((ip, channels)) if ip == sender.ip() => {
+                                route.pending = None;
+                                route.send(channels, route_update);
                             },
+                            _ => {},
+                        }
+                    }
+                },
+                Ok(InputEvent::Address(sender, address)) => {
+                    info!("Recieved ArtAddress from {}", sender);
+                    apply_address(&address, &mut arguments, &mut outputs, &mut update, &node, sacn.as_ref());
+                    update_node_info(&node, &outputs);
+                    if let Some(responder) = &responder {
+                        responder.reply(sender);
+                    }
+                    update.reconfigured = Some(arguments.clone());
+                },
+                Ok(InputEvent::Rejected(sender, protocol, packets)) => {
+                    if statistics.rejected(sender, protocol, packets) {
+                        warn!("Rejecting {} from {}, it isn't an allowed controller", protocol, sender);
+                    }
+                },
+                Ok(InputEvent::Command(RunnerCommand::StartRecording(path))) => {
+                    let writer = match ShowWriter::create(&path) {
+                        Ok(writer) => writer,
+                        Err(error) => {
+                            error!("Couldn't create show file \"{}\": {}", path.display(), error);
+                            continue;
+                        },
+                    };
+                    info!("Recording to \"{}\"", path.display());
+                    if let Ok(mut recorder) = recorder.lock() {
+                        finish_recording(recorder.replace(writer));
+                    }
+                },
+                Ok(InputEvent::Command(RunnerCommand::StopRecording)) => {
+                    if let Ok(mut recorder) = recorder.lock() {
+                        finish_recording(recorder.take());
+                    }
+                },
+                Ok(InputEvent::Command(RunnerCommand::Blackout(blackout))) => {
+                    match blackout {
+                        true => info!("Blackout"),
+                        false => info!("Blackout released"),
+                    }
+                    update.blackout = blackout;
+                    for (route_update, route) in update.routes.iter_mut().zip(outputs.iter_mut()) {
+                        route.update_overrides(|overrides| overrides.blackout = blackout, route_update);
+                    }
+                },
+                Ok(InputEvent::Command(RunnerCommand::Master(master))) => {
+                    debug!("Master at {:.0}%", master * 100.0);
+                    update.master = master;
+                    for (route_update, route) in update.routes.iter_mut().zip(outputs.iter_mut()) {
+                        route.update_overrides(|overrides| overrides.master = master, route_update);
+                    }
+                },
+                Ok(InputEvent::Command(RunnerCommand::Channel { universe, channel, value })) => {
+                    match value {
+                        Some(value) => debug!("Holding channel {} at {}", channel, value),
+                        None => debug!("Releasing channel {}", channel),
+                    }
+                    for (route_update, route) in update.routes.iter_mut().zip(outputs.iter_mut()) {
+                        if universe.is_none_or(|universe| universe == route_update.universe) {
+                            route.update_overrides(|overrides| overrides.channels[channel as usize - 1] = value, route_update);
                         }
-                        debug!("Updated dmx channels on interface");
                     }
                 },
-                Err(mpsc::TryRecvError::Empty) => {
-                    update.connected_to_artnet = true;
-                    std::thread::sleep(std::time::Duration::from_millis(1));
+                Ok(InputEvent::Command(RunnerCommand::ReleaseChannels)) => {
+                    info!("Releasing all held channels");
+                    for (route_update, route) in update.routes.iter_mut().zip(outputs.iter_mut()) {
+                        route.update_overrides(|overrides| overrides.channels = [None; 512], route_update);
+                    }
+                },
+                Ok(InputEvent::Command(RunnerCommand::Playback(command))) => match &player {
+                    Some(player) => player.send(command),
+                    None => warn!("No show file to play, command ignored"),
                 },
-                Err(mpsc::TryRecvError::Disconnected) => {
-                    error!("Art-net reciever disconnected");
-                    std::thread::sleep(std::time::Duration::from_millis(1));
+                Err(mpsc::RecvTimeoutError::Timeout) => {},
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    error!("Network listeners disconnected");
+                    update.connected_to_artnet = false;
+                    thread::sleep(TICK_INTERVAL);
                 },
+            }
+            if Instant::now() < next_tick {
+                continue;
+            }
+            next_tick += TICK_INTERVAL;
+            if next_tick < Instant::now() {
+                // Don't catch up on ticks missed while blocked on the interface
+                next_tick = Instant::now() + TICK_INTERVAL;
+            }
+
+            if update.synchronous && last_sync.is_none_or(|instant| instant.elapsed() > SYNC_TIMEOUT) {
+                info!("No ArtSync for {} seconds. Switching to immediate output", SYNC_TIMEOUT.as_secs());
+                update.synchronous = false;
+                last_sync = None;
+                for (route_update, route) in update.routes.iter_mut().zip(outputs.iter_mut()) {
+                    if let Some((_, channels)) = route.pending.take() {
+                        route.send(channels, route_update);
+                    }
+                }
+            }
+            let mut merge_changed = false;
+            for (route_update, route) in update.routes.iter_mut().zip(outputs.iter_mut()) {
+                if route.merger.expire() {
+                    debug!("Merge source on universe {} timed out", route_update.universe);
+                    route.send(route.merger.output(), route_update);
+                }
+                if route_update.sources != route.merger.source_count() {
+                    if route.merger.is_merging() {
+                        info!("Merging {} sources on universe {} ({})", route.merger.source_count(), route_update.universe, route.merger.mode());
+                    }
+                    route_update.sources = route.merger.source_count();
+                    merge_changed = true;
+                }
+                route.supervise(route_update);
+                route.check_signal(route_update);
+                route.refresh(route_update);
+            }
+            if merge_changed {
+                update_node_info(&node, &outputs);
+            }
+            if let Ok(mut recorder) = recorder.lock() {
+                if let Some(Err(error)) = recorder.as_mut().map(ShowWriter::flush) {
+                    error!("Couldn't write show file: {}. Stopping recording", error);
+                    *recorder = None;
+                }
+                update.recording = recorder.is_some();
+            }
+            update.playback = player.as_ref().and_then(playback::Player::status);
+            if statistics.update(outputs.iter().map(|route| route.counters).fold(OutputCounters::default(), |a, b| a + b)) {
+                update.statistics = statistics.statistics().clone();
+                debug!("Statistics: {}", update.statistics);
+            }
 
+            if last_update.as_ref().is_some_and(|(instant, last)| *last == update || instant.elapsed() < UPDATE_INTERVAL) {
+                continue;
             }
-            update.connected_to_dmx = dmx.check_agent().is_ok();
-            match tx.try_send(update) {
-                Ok(_) => {},
+            match tx.try_send(update.clone()) {
+                Ok(_) => {
+                    last_update = Some((Instant::now(), update.clone()));
+                    update.reconfigured = None;
+                    for (route_update, route) in update.routes.iter_mut().zip(outputs.iter()) {
+                        route_update.dmx_sent = !route.device.is_sync();
+                        route_update.dmx_recieved = None;
+                    }
+                },
                 Err(mpsc::TrySendError::Full(_)) => {},
                 Err(mpsc::TrySendError::Disconnected(_)) => {
                     warn!("Update channel disconnected. Stopping runner...");
@@ -182,27 +738,353 @@ pub fn create_runner(arguments: Arguments) -> Result<RunnerUpdateReciever, Runne
                 },
             }
         }
+        info!("Stopping runner...");
+        running.store(false, Ordering::Relaxed);
+        for listener in listeners {
+            let _ = listener.join();
+        }
+        if let Some(player) = player {
+            player.join();
+        }
+        // Sync mode lets the interface threads release their ports right away
+        for route in outputs.iter_mut() {
+            route.device.set_sync();
+        }
+        drop(outputs);
+        if let Ok(mut recorder) = recorder.lock() {
+            finish_recording(recorder.take());
+        }
+        info!("Stopped!");
     });
-    Ok(rx)
+    Ok(RunnerHandle {
+        updates: rx,
+        commands: input_tx,
+        running,
+        thread: Some(thread),
+    })
+}
+
+///Records the `universes` to a show file without any dmx output, until Ctrl-C or a termination signal
+pub fn record(arguments: RecordArguments) -> Result<(), RunnerCreationError> {
+    let mut writer = match ShowWriter::create(&arguments.path) {
+        Ok(writer) => writer,
+        Err(error) => {
+            error!("Couldn't create show file: {}", error);
+            return Err(RunnerCreationError::RecordingError(error));
+        },
+    };
+    let node = Arc::new(Mutex::new(NodeInfo {
+        short_name: arguments.options.short_name.clone().unwrap_or("artnet2opendmx".into()),
+        long_name: arguments.options.name.clone().unwrap_or("artnet_to_opendmx_node".into()),
+        ports: arguments.universes.iter().map(|universe| PortInfo {
+            universe: *universe,
+            good_output: 0,
+        }).collect(),
+    }));
+    let (input_tx, input_rx) = mpsc::channel();
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_running = running.clone();
+    if let Err(error) = ctrlc::set_handler(move || handler_running.store(false, Ordering::Relaxed)) {
+        warn!("Couldn't handle Ctrl-C, the end of the recording might get lost: {}", error);
+    }
+    let _listeners = start_listeners(&arguments.options, &arguments.universes, node, input_tx, running.clone())?;
+    let mut mergers = arguments.universes.iter().map(|universe| (*universe, Merger::new(arguments.options.merge))).collect::<Vec<_>>();
+    info!("Recording universes {:?} to \"{}\"", arguments.universes, arguments.path.display());
+
+    let mut statistics = StatisticsCollector::new();
+    let mut frames = OutputCounters::default();
+    while running.load(Ordering::Relaxed) {
+        let frame = match input_rx.recv_timeout(TICK_INTERVAL) {
+            Ok(InputEvent::Dmx(frame)) => frame,
+            Ok(InputEvent::Rejected(sender, protocol, packets)) => {
+                if statistics.rejected(sender, protocol, packets) {
+                    warn!("Rejecting {} from {}, it isn't an allowed controller", protocol, sender);
+                }
+                continue;
+            },
+            Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => {
+                if let Err(error) = writer.flush() {
+                    error!("Couldn't record frame: {}", error);
+                    return Err(RunnerCreationError::RecordingWriteError(error));
+                }
+                if statistics.update(frames) {
+                    debug!("Statistics: {}", statistics.statistics());
+                }
+                continue;
+            },
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+        let merger = mergers.iter_mut().find(|(universe, _)| *universe == frame.universe);
+        statistics.packet(frame.sender.ip(), frame.protocol, merger.is_some());
+        if let Some((universe, merger)) = merger {
+            merger.expire();
+            let channels = merger.merge(frame.sender, frame.channels);
+            if let Err(error) = writer.write_frame(*universe, &channels) {
+                error!("Couldn't record frame: {}", error);
+                return Err(RunnerCreationError::RecordingWriteError(error));
+            }
+            frames.frames += 1;
+        }
+    }
+    info!("Stopping...");
+    finish_recording(Some(writer));
+    Ok(())
+}
+
+fn finish_recording(writer: Option<ShowWriter>) {
+    if let Some(writer) = writer {
+        match writer.finish() {
+            Ok(_) => info!("Recording stopped"),
+            Err(error) => error!("Couldn't finish recording: {}", error),
+        }
+    }
+}
+
+///The running network listeners
+pub struct Listeners {
+    ///Answers ArtAddress, if Art-Net is enabled
+    pub responder: Option<Responder>,
+    ///The multicast groups of the sACN listener, if it's enabled
+    pub sacn: Option<SacnGroups>,
+    pub threads: Vec<JoinHandle<()>>,
+}
+
+///Starts the network listeners selected by `options` for `universes`
+///
+///All listeners stop as soon as `running` is cleared.
+pub fn start_listeners(options: &Options, universes: &[u16], node: SharedNodeInfo, input_tx: mpsc::Sender<InputEvent>, running: Arc<AtomicBool>) -> Result<Listeners, RunnerCreationError> {
+    let controller = options.controller.clone().filter(|controller| !controller.allows_all());
+    let device = match &options.interface {
+        Some(name) => match network::find(name) {
+            Some(device) => {
+                info!("Listening on interface {}", device);
+                Some(device)
+            },
+            None => {
+                error!("Couldn't find network interface \"{}\"", name);
+                return Err(RunnerCreationError::InterfaceError(name.clone()));
+            },
+        },
+        None => None,
+    };
+    let mut listeners = Vec::new();
+    let responder = match options.input.artnet() {
+        true => {
+            let (responder, listener) = start_artnet_listener(options, device.as_ref(), controller.clone(), node, input_tx.clone(), running.clone())?;
+            listeners.push(listener);
+            Some(responder)
+        },
+        false => None,
+    };
+    if let Some(port) = options.osc {
+        info!("Starting OSC listener...");
+        let address = SocketAddrV4::new(options.bind_ip.unwrap_or(Ipv4Addr::UNSPECIFIED), port);
+        match osc::start_reciever(address, device.as_ref(), controller.clone(), input_tx.clone(), running.clone()) {
+            Ok(listener) => listeners.push(listener),
+            Err(error) => {
+                error!("Couldn't create OSC reciever: {}", error);
+                running.store(false, Ordering::Relaxed);
+                return Err(RunnerCreationError::OscCreationError(error));
+            },
+        }
+        info!("Started!");
+    }
+    let mut sacn = None;
+    if options.input.sacn() {
+        info!("Starting sACN listener...");
+        // sACN is bound to the interface of the local address instead of the address itself
+        let device = match (device, options.bind_ip.filter(|ip| !ip.is_unspecified())) {
+            (Some(device), _) => Some(device),
+            (None, Some(ip)) => match network::find_by_ip(ip) {
+                Some(device) => Some(device),
+                None => {
+                    error!("Couldn't find a network interface with the address {}", ip);
+                    running.store(false, Ordering::Relaxed);
+                    return Err(RunnerCreationError::SacnCreationError(std::io::Error::new(std::io::ErrorKind::AddrNotAvailable, format!("No network interface has the address {}", ip))));
+                },
+            },
+            (None, None) => None,
+        };
+        match sacn::start_reciever(universes, device.as_ref(), controller, input_tx, running.clone()) {
+            Ok((groups, listener)) => {
+                sacn = Some(groups);
+                listeners.push(listener);
+            },
+            Err(error) => {
+                error!("Couldn't create sACN reciever: {}", error);
+                running.store(false, Ordering::Relaxed);
+                return Err(RunnerCreationError::SacnCreationError(error));
+            },
+        }
+        info!("Started!");
+    }
+    Ok(Listeners {
+        responder,
+        sacn,
+        threads: listeners,
+    })
+}
+
+fn start_artnet_listener(options: &Options, device: Option<&NetworkInterface>, controller: Option<SenderFilter>, node: SharedNodeInfo, input_tx: mpsc::Sender<InputEvent>, running: Arc<AtomicBool>) -> Result<(Responder, JoinHandle<()>), RunnerCreationError> {
+    info!("Starting art-net listener...");
+
+    let address = SocketAddrV4::new(options.bind_ip.unwrap_or(Ipv4Addr::UNSPECIFIED), options.port.unwrap_or(6454));
+    let listener = match artnet::start_reciever(address, device, controller, node, input_tx, running) {
+        Ok(listener) => listener,
+        Err(error) => {
+            error!("Couldn't create art-net reciever: {}", error);
+            return Err(RunnerCreationError::ArtnetCreationError(error));
+        },
+    };
+    info!("Started!");
+    Ok(listener)
+}
+
+///Applies a remote reconfiguration to the running routes and `arguments`
+fn apply_address(address: &ArtAddress, arguments: &mut Arguments, outputs: &mut [RouteOutput], update: &mut RunnerUpdate, node: &SharedNodeInfo, sacn: Option<&SacnGroups>) {
+    let mut node = match node.lock() {
+        Ok(node) => node,
+        Err(_) => return,
+    };
+    if let Some(name) = &address.short_name {
+        info!("Controller renamed node to \"{}\"", name);
+        node.short_name = name.clone();
+        arguments.options.short_name = Some(name.clone());
+    }
+    if let Some(name) = &address.long_name {
+        info!("Controller set long name to \"{}\"", name);
+        node.long_name = name.clone();
+        arguments.options.name = Some(name.clone());
+    }
+    // Every route is reported as its own bound node, starting at index 1
+    let index = address.bind_index.max(1) as usize - 1;
+    let universes = outputs.iter().map(|route| u16::from(route.universe)).collect::<Vec<_>>();
+    match (outputs.get_mut(index), update.routes.get_mut(index), node.ports.get_mut(index)) {
+        (Some(route), Some(route_update), Some(port)) => {
+            let universe = address.universe(route_update.universe, route.initial_universe);
+            let moved = match (universe != route_update.universe, PortAddress::try_from(universe)) {
+                (true, Ok(port_address)) => match sacn.map(|groups| move_sacn_group(groups, &universes, index, universe)) {
+                    Some(Err(error)) => {
+                        warn!("Couldn't listen to sACN universe {}, keeping universe {}: {}", universe, route_update.universe, error);
+                        None
+                    },
+                    _ => Some(port_address),
+                },
+                _ => None,
+            };
+            if let Some(port_address) = moved {
+                info!("Controller moved \"{}\" from universe {} to {}", route.device.name(), route_update.universe, universe);
+                route.universe = port_address;
+                route.pending = None;
+                route_update.universe = universe;
+                port.universe = universe;
+                arguments.routes[index].universe = universe;
+            }
+            if address.command == AddressCommand::CancelMerge {
+                info!("Controller cancelled the merge on universe {}", universe);
+                route.merger.cancel_merge();
+            }
+        },
+        _ => warn!("ArtAddress for unknown bind index {}", address.bind_index),
+    }
+    match address.command {
+        AddressCommand::Merge(mode) => {
+            info!("Controller switched merge mode to {}", mode);
+            arguments.options.merge = mode;
+            outputs.iter_mut().for_each(|route| route.merger.set_mode(mode));
+        },
+        AddressCommand::Other(command) => debug!("Ignoring unsupported ArtAddress command {:#04x}", command),
+        AddressCommand::None | AddressCommand::CancelMerge => {},
+    }
+}
+
+///Moves the sACN group of route `index` to `universe`, keeping the groups other routes still listen to
+fn move_sacn_group(groups: &SacnGroups, universes: &[u16], index: usize, universe: u16) -> std::io::Result<()> {
+    let others = || universes.iter().enumerate().filter(|(i, _)| *i != index).map(|(_, universe)| *universe);
+    if !others().any(|other| other == universe) {
+        groups.join(universe)?;
+    }
+    if !others().any(|other| other == universes[index]) {
+        if let Err(error) = groups.leave(universes[index]) {
+            warn!("Couldn't leave sACN universe {}: {}", universes[index], error);
+        }
+    }
+    Ok(())
+}
+
+fn update_node_info(node: &SharedNodeInfo, outputs: &[RouteOutput]) {
+    if let Ok(mut node) = node.lock() {
+        for (port, route) in node.ports.iter_mut().zip(outputs.iter()) {
+            port.good_output = route.good_output();
+        }
+    }
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum RunnerCreationError {
-    PortListingError(serialport::Error),
-    LocateDeviceError,
-    DeviceOpeningError(serialport::Error),
-    DeviceUpdateError(open_dmx::error::DMXDisconnectionError),
+    NoRoutesError,
+    InvalidUniverseError(u16),
+    InterfaceError(String),
+    InvalidDeviceError(String),
+    DeviceOpeningError(OutputError),
     ArtnetCreationError(std::io::Error),
+    SacnCreationError(std::io::Error),
+    OscCreationError(std::io::Error),
+    SceneLoadingError(scene::SceneError),
+    RecordingError(std::io::Error),
+    RecordingWriteError(std::io::Error),
+    PlaybackError(std::io::Error),
 }
 
 impl Display for RunnerCreationError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            RunnerCreationError::PortListingError(e) => write!(f, "Couldn't list serial ports: {}", e),
-            RunnerCreationError::LocateDeviceError => write!(f, "Couldn't find device"),
+            RunnerCreationError::NoRoutesError => write!(f, "No routes configured"),
+            RunnerCreationError::InvalidUniverseError(universe) => write!(f, "Invalid universe {}", universe),
+            RunnerCreationError::InterfaceError(name) => write!(f, "Couldn't find network interface \"{}\"", name),
+            RunnerCreationError::InvalidDeviceError(e) => write!(f, "{}", e),
             RunnerCreationError::DeviceOpeningError(e) => write!(f, "Couldn't open device: {}", e),
-            RunnerCreationError::DeviceUpdateError(e) => write!(f, "Couldn't update device: {}", e),
             RunnerCreationError::ArtnetCreationError(e) => write!(f, "Couldn't create art-net reciever: {}", e),
+            RunnerCreationError::SacnCreationError(e) => write!(f, "Couldn't create sACN reciever: {}", e),
+            RunnerCreationError::OscCreationError(e) => write!(f, "Couldn't create OSC reciever: {}", e),
+            RunnerCreationError::SceneLoadingError(e) => write!(f, "Couldn't load failsafe scene: {}", e),
+            RunnerCreationError::RecordingError(e) => write!(f, "Couldn't create show file: {}", e),
+            RunnerCreationError::RecordingWriteError(e) => write!(f, "Couldn't write show file: {}", e),
+            RunnerCreationError::PlaybackError(e) => write!(f, "Couldn't open show file: {}", e),
         }
     }    
-}
\ No newline at end of file
+}
+
+impl std::error::Error for RunnerCreationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_rejected_packets_once_per_interval() {
+        let (tx, rx) = mpsc::channel();
+        let mut rejections = Rejections::new(Protocol::Sacn);
+        let sender = IpAddr::from([10, 0, 0, 1]);
+        for _ in 0..100 {
+            rejections.count(sender);
+            rejections.report(&tx).unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+
+        rejections.last_report -= REJECTION_REPORT_INTERVAL;
+        rejections.count(IpAddr::from([10, 0, 0, 2]));
+        rejections.report(&tx).unwrap();
+        let mut reports = rx.try_iter().map(|event| match event {
+            InputEvent::Rejected(sender, Protocol::Sacn, packets) => (sender, packets),
+            _ => panic!("Unexpected event"),
+        }).collect::<Vec<_>>();
+        reports.sort();
+        assert_eq!(reports, vec![(sender, 100), (IpAddr::from([10, 0, 0, 2]), 1)]);
+
+        rejections.last_report -= REJECTION_REPORT_INTERVAL;
+        rejections.report(&tx).unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+}
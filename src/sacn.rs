@@ -0,0 +1,257 @@
+//! A minimal sACN (ANSI E1.31) reciever
+use std::{collections::HashMap, net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket}, sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc}, thread::{self, JoinHandle}, time::{Duration, Instant}};
+
+use socket2::Socket;
+use log::debug;
+
+use crate::filter::SenderFilter;
+use crate::network::{self, NetworkInterface};
+use crate::runner::{self, InputEvent, InputFrame, Protocol, Rejections, LISTENER_TIMEOUT};
+
+pub const SACN_PORT: u16 = 5568;
+
+const ACN_PACKET_IDENTIFIER: [u8; 12] = *b"ASC-E1.17\0\0\0";
+const VECTOR_ROOT_E131_DATA: u32 = 0x0000_0004;
+const VECTOR_E131_DATA_PACKET: u32 = 0x0000_0002;
+const VECTOR_DMP_SET_PROPERTY: u8 = 0x02;
+
+const OPTION_PREVIEW_DATA: u8 = 0b1000_0000;
+const OPTION_STREAM_TERMINATED: u8 = 0b0100_0000;
+
+const HEADER_LENGTH: usize = 126;
+
+///Sources which haven't sent anything for this long are considered lost (E1.31 network data loss)
+const SOURCE_TIMEOUT: Duration = Duration::from_millis(2500);
+
+///A parsed E1.31 data packet
+#[derive(Debug)]
+pub struct SacnPacket<'a> {
+    pub cid: [u8; 16],
+    pub priority: u8,
+    pub sequence: u8,
+    pub options: u8,
+    ///The sACN universe (1-63999)
+    pub universe: u16,
+    pub start_code: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> SacnPacket<'a> {
+    ///Parses an E1.31 data packet. Returns `None` for anything that isn't a valid data packet.
+    pub fn parse(buffer: &'a [u8]) -> Option<Self> {
+        if buffer.len() < HEADER_LENGTH {
+            return None;
+        }
+        if buffer[0..2] != [0x00, 0x10] || buffer[4..16] != ACN_PACKET_IDENTIFIER {
+            return None;
+        }
+        if u32::from_be_bytes(buffer[18..22].try_into().ok()?) != VECTOR_ROOT_E131_DATA {
+            return None;
+        }
+        if u32::from_be_bytes(buffer[40..44].try_into().ok()?) != VECTOR_E131_DATA_PACKET {
+            return None;
+        }
+        if buffer[117] != VECTOR_DMP_SET_PROPERTY || buffer[118] != 0xa1 {
+            return None;
+        }
+        let value_count = u16::from_be_bytes([buffer[123], buffer[124]]) as usize;
+        if value_count == 0 || buffer.len() < 125 + value_count {
+            return None;
+        }
+        Some(SacnPacket {
+            cid: buffer[22..38].try_into().ok()?,
+            priority: buffer[108],
+            sequence: buffer[111],
+            options: buffer[112],
+            universe: u16::from_be_bytes([buffer[113], buffer[114]]),
+            start_code: buffer[125],
+            data: &buffer[126..125 + value_count],
+        })
+    }
+}
+
+///The multicast group an sACN universe is sent to
+pub fn multicast_address(universe: u16) -> Ipv4Addr {
+    let [high, low] = universe.to_be_bytes();
+    Ipv4Addr::new(239, 255, high, low)
+}
+
+///Converts a route universe into the matching sACN universe
+///
+///Art-Net starts counting at universe 0, while sACN starts at 1.
+pub fn sacn_universe(universe: u16) -> u16 {
+    universe + 1
+}
+
+///The multicast groups of a running reciever, joined on the interface with the address `interface`
+#[derive(Clone)]
+pub struct SacnGroups {
+    socket: Arc<UdpSocket>,
+    interface: Ipv4Addr,
+}
+
+impl SacnGroups {
+    ///Joins the group of the route universe `universe`
+    pub fn join(&self, universe: u16) -> std::io::Result<()> {
+        let group = multicast_address(sacn_universe(universe));
+        debug!("Joining sACN multicast group {}", group);
+        self.socket.join_multicast_v4(&group, &self.interface)
+    }
+
+    pub fn leave(&self, universe: u16) -> std::io::Result<()> {
+        let group = multicast_address(sacn_universe(universe));
+        debug!("Leaving sACN multicast group {}", group);
+        self.socket.leave_multicast_v4(&group, &self.interface)
+    }
+}
+
+///Starts a thread recieving sACN on all given route universes and forwarding them to `tx`
+///
+///The multicast groups are joined on `device`, on the default interface if there is none.
+pub fn start_reciever(universes: &[u16], device: Option<&NetworkInterface>, controller: Option<SenderFilter>, tx: mpsc::Sender<InputEvent>, running: Arc<AtomicBool>) -> std::io::Result<(SacnGroups, JoinHandle<()>)> {
+    let socket = Socket::new(socket2::Domain::IPV4, socket2::Type::DGRAM, Some(socket2::Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    // Multicast doesn't arrive on sockets bound to a local address, so stay on the unspecified one
+    // and restrict the socket to the device where possible
+    if let Some(device) = device {
+        network::bind_device(&socket, device);
+    }
+    socket.bind(&SocketAddr::from(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, SACN_PORT)).into())?;
+    let socket = Arc::new(UdpSocket::from(socket));
+    socket.set_read_timeout(Some(LISTENER_TIMEOUT))?;
+    let groups = SacnGroups {
+        socket: socket.clone(),
+        interface: device.map_or(Ipv4Addr::UNSPECIFIED, |device| device.ip),
+    };
+    for (i, universe) in universes.iter().enumerate() {
+        // Routes may share a universe
+        if !universes[..i].contains(universe) {
+            groups.join(*universe)?;
+        }
+    }
+
+    Ok((groups, thread::spawn(move || {
+        let mut buffer = [0; 1144];
+        let mut sequences: HashMap<([u8; 16], u16), u8> = HashMap::new();
+        let mut priorities: HashMap<([u8; 16], u16), (u8, Instant)> = HashMap::new();
+        let mut rejections = Rejections::new(Protocol::Sacn);
+        while running.load(Ordering::Relaxed) {
+            if rejections.report(&tx).is_err() {
+                debug!("sACN reciever disconnected");
+                break;
+            }
+            let (size, sender) = match socket.recv_from(&mut buffer) {
+                Ok(packet) => packet,
+                Err(error) => {
+                    runner::listener_error("sACN", error);
+                    continue;
+                },
+            };
+            let packet = match SacnPacket::parse(&buffer[..size]) {
+                Some(packet) => packet,
+                None => continue,
+            };
+            // Before the priorities, a rejected source mustn't win over the allowed ones
+            if controller.as_ref().is_some_and(|controller| !controller.allows(sender.ip())) {
+                rejections.count(sender.ip());
+                continue;
+            }
+            if let Some(last) = sequences.get(&(packet.cid, packet.universe)) {
+                let difference = packet.sequence.wrapping_sub(*last) as i8;
+                if difference <= 0 && difference > -20 {
+                    debug!("Dropping out of sequence sACN packet from {}", sender);
+                    continue;
+                }
+            }
+            sequences.insert((packet.cid, packet.universe), packet.sequence);
+            if packet.options & OPTION_STREAM_TERMINATED != 0 {
+                debug!("sACN source {} terminated universe {}", sender, packet.universe);
+                sequences.remove(&(packet.cid, packet.universe));
+                priorities.remove(&(packet.cid, packet.universe));
+                continue;
+            }
+            if packet.options & OPTION_PREVIEW_DATA != 0 || packet.start_code != 0 || packet.universe == 0 {
+                continue;
+            }
+            priorities.retain(|_, (_, last_seen)| last_seen.elapsed() < SOURCE_TIMEOUT);
+            priorities.insert((packet.cid, packet.universe), (packet.priority, Instant::now()));
+            let highest_priority = priorities.iter()
+                .filter(|((_, universe), _)| *universe == packet.universe)
+                .map(|(_, (priority, _))| *priority)
+                .max()
+                .unwrap_or(packet.priority);
+            if packet.priority < highest_priority {
+                continue;
+            }
+            let mut channels = [0; 512];
+            packet.data.iter().zip(channels.iter_mut()).for_each(|(a, b)| *b = *a);
+            let frame = InputFrame {
+                sender,
+                universe: packet.universe - 1, //inverse of sacn_universe
+                channels,
+                protocol: Protocol::Sacn,
+            };
+            if tx.send(InputEvent::Dmx(Box::new(frame))).is_err() {
+                debug!("sACN reciever disconnected");
+                break;
+            }
+        }
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_packet(universe: u16, priority: u8, sequence: u8, channels: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0; HEADER_LENGTH + channels.len()];
+        packet[0..2].copy_from_slice(&[0x00, 0x10]);
+        packet[4..16].copy_from_slice(&ACN_PACKET_IDENTIFIER);
+        packet[18..22].copy_from_slice(&VECTOR_ROOT_E131_DATA.to_be_bytes());
+        packet[22..38].copy_from_slice(&[7; 16]);
+        packet[40..44].copy_from_slice(&VECTOR_E131_DATA_PACKET.to_be_bytes());
+        packet[108] = priority;
+        packet[111] = sequence;
+        packet[113..115].copy_from_slice(&universe.to_be_bytes());
+        packet[117] = VECTOR_DMP_SET_PROPERTY;
+        packet[118] = 0xa1;
+        packet[123..125].copy_from_slice(&(channels.len() as u16 + 1).to_be_bytes());
+        packet[126..].copy_from_slice(channels);
+        packet
+    }
+
+    #[test]
+    fn parses_data_packets() {
+        let buffer = data_packet(3, 120, 42, &[1, 2, 3]);
+        let packet = SacnPacket::parse(&buffer).unwrap();
+        assert_eq!(packet.cid, [7; 16]);
+        assert_eq!(packet.priority, 120);
+        assert_eq!(packet.sequence, 42);
+        assert_eq!(packet.options, 0);
+        assert_eq!(packet.universe, 3);
+        assert_eq!(packet.start_code, 0);
+        assert_eq!(packet.data, [1, 2, 3]);
+    }
+
+    #[test]
+    fn rejects_other_packets() {
+        let valid = data_packet(1, 100, 0, &[0; 512]);
+        assert!(SacnPacket::parse(&valid[..HEADER_LENGTH - 1]).is_none());
+        let mut identifier = valid.clone();
+        identifier[4] = b'X';
+        assert!(SacnPacket::parse(&identifier).is_none());
+        // E1.31 synchronization packets use another root vector
+        let mut sync = valid.clone();
+        sync[18..22].copy_from_slice(&8u32.to_be_bytes());
+        assert!(SacnPacket::parse(&sync).is_none());
+        let mut truncated = valid.clone();
+        truncated.truncate(300);
+        assert!(SacnPacket::parse(&truncated).is_none());
+    }
+
+    #[test]
+    fn maps_universes_to_multicast_groups() {
+        assert_eq!(multicast_address(sacn_universe(0)), Ipv4Addr::new(239, 255, 0, 1));
+        assert_eq!(multicast_address(63999), Ipv4Addr::new(239, 255, 249, 255));
+    }
+}
@@ -0,0 +1,76 @@
+//! Static dmx scenes, used as failsafe look when the signal gets lost
+use std::{fmt::{Display, Formatter}, path::Path};
+
+pub type Scene = [u8; 512];
+
+pub fn load(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+    let text = std::fs::read_to_string(path.as_ref()).map_err(|error| SceneError::Io(path.as_ref().display().to_string(), error))?;
+    parse(&text)
+}
+
+///One channel or range per line like `1-4 = 255`, the others stay at zero
+pub fn parse(text: &str) -> Result<Scene, SceneError> {
+    let mut scene = [0; 512];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: &str| SceneError::Syntax(number + 1, message.into());
+        let (channels, value) = line.split_once('=').ok_or_else(|| error("Expected <CHANNELS> = <VALUE>"))?;
+        let value = value.trim().parse::<u8>().map_err(|_| error("Invalid value (0-255)"))?;
+        let channel = |s: &str| match s.trim().parse::<usize>() {
+            Ok(channel @ 1..=512) => Ok(channel),
+            _ => Err(error("Invalid channel (1-512)")),
+        };
+        let (start, end) = match channels.split_once('-') {
+            Some((start, end)) => (channel(start)?, channel(end)?),
+            None => (channel(channels)?, channel(channels)?),
+        };
+        if start > end {
+            return Err(error("Invalid range"));
+        }
+        scene[start - 1..end].fill(value);
+    }
+    Ok(scene)
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(String, std::io::Error),
+    Syntax(usize, String),
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(path, e) => write!(f, "Couldn't read scene file \"{}\": {}", path, e),
+            SceneError::Syntax(line, message) => write!(f, "Invalid scene in line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets_channels_and_ranges() {
+        let scene = parse("# House lights at full\n1-4 = 255\n\n10 = 128 # half\n").unwrap();
+        assert_eq!(scene[..5], [255, 255, 255, 255, 0]);
+        assert_eq!(scene[9], 128);
+        assert_eq!(scene.iter().filter(|value| **value != 0).count(), 5);
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        for (text, line) in [("1 = 2\n1 2", 2), ("0 = 1", 1), ("513 = 1", 1), ("1 = 256", 1), ("4-1 = 1", 1)] {
+            match parse(text) {
+                Err(SceneError::Syntax(error_line, _)) => assert_eq!(error_line, line, "{text}"),
+                result => panic!("{text} parsed to {result:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Show files, recordings of the dmx output
+use std::{collections::HashMap, fs::File, io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write}, path::Path, time::{Duration, Instant}};
+
+use log::warn;
+
+pub const MAGIC: &[u8; 7] = b"A2DSHOW";
+pub const VERSION: u8 = 1;
+pub const FRAME_FULL: u8 = 0;
+pub const FRAME_DELTA: u8 = 1;
+///Buffered frames are written to disk at least this often while frames arrive
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+///Writes [`MAGIC`] and [`VERSION`], then per frame its time in milliseconds (`u32`), universe (`u16`) and kind (`u8`),
+///followed by all 512 channels or, for a delta, the count (`u16`) and the index (`u16`) and value (`u8`) of every change.
+///Numbers are little endian, the first frame of a universe is always full.
+pub struct ShowWriter {
+    file: BufWriter<File>,
+    start: Instant,
+    last_flush: Instant,
+    ///The last frame of every universe
+    frames: HashMap<u16, [u8; 512]>,
+}
+
+impl ShowWriter {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION])?;
+        Ok(Self {
+            file,
+            start: Instant::now(),
+            last_flush: Instant::now(),
+            frames: HashMap::new(),
+        })
+    }
+
+    pub fn write_frame(&mut self, universe: u16, channels: &[u8; 512]) -> io::Result<()> {
+        let time = self.start.elapsed().as_millis().min(u32::MAX as u128) as u32;
+        self.file.write_all(&time.to_le_bytes())?;
+        self.file.write_all(&universe.to_le_bytes())?;
+        let changes = self.frames.get(&universe).map(|last| {
+            last.iter().zip(channels.iter()).enumerate()
+                .filter(|(_, (old, new))| old != new)
+                .map(|(index, (_, new))| (index as u16, *new))
+                .collect::<Vec<_>>()
+        });
+        match changes {
+            // A delta needs 3 bytes per change
+            Some(changes) if 2 + changes.len() * 3 < channels.len() => {
+                self.file.write_all(&[FRAME_DELTA])?;
+                self.file.write_all(&(changes.len() as u16).to_le_bytes())?;
+                for (index, value) in changes {
+                    self.file.write_all(&index.to_le_bytes())?;
+                    self.file.write_all(&[value])?;
+                }
+            },
+            _ => {
+                self.file.write_all(&[FRAME_FULL])?;
+                self.file.write_all(channels)?;
+            },
+        }
+        self.frames.insert(universe, *channels);
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    ///Writes all buffered frames to disk
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.file.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+///A decoded frame of a show file
+pub struct ShowFrame {
+    ///The time since the start of the recording
+    pub time: Duration,
+    pub universe: u16,
+    pub channels: [u8; 512],
+}
+
+///Reads show files frame by frame, without loading them into memory
+pub struct ShowReader {
+    file: BufReader<File>,
+    frames: HashMap<u16, [u8; 512]>,
+    length: Duration,
+    ///The file ends in the middle of a frame
+    truncated: bool,
+}
+
+impl ShowReader {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut header = [0; 8];
+        file.read_exact(&mut header)?;
+        if &header[..7] != MAGIC || header[7] != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a show file (or an unsupported version)"));
+        }
+        let mut reader = Self {
+            file,
+            frames: HashMap::new(),
+            length: Duration::ZERO,
+            truncated: false,
+        };
+        while let Some(frame) = reader.next_frame()? {
+            reader.length = frame.time;
+        }
+        if reader.truncated {
+            warn!("The show file ends in the middle of a frame, it ends with the last complete one");
+        }
+        reader.rewind()?;
+        Ok(reader)
+    }
+
+    ///The time of the last frame
+    pub fn length(&self) -> Duration {
+        self.length
+    }
+
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.frames.clear();
+        self.file.seek(SeekFrom::Start(MAGIC.len() as u64 + 1))?;
+        Ok(())
+    }
+
+    ///Reads the next frame, `None` at the end of the show
+    pub fn next_frame(&mut self) -> io::Result<Option<ShowFrame>> {
+        match self.read_frame() {
+            // A recording which got killed can stop in the middle of a frame
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                self.truncated = true;
+                Ok(None)
+            },
+            result => result,
+        }
+    }
+
+    fn read_frame(&mut self) -> io::Result<Option<ShowFrame>> {
+        if self.file.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let mut header = [0; 7];
+        self.file.read_exact(&mut header)?;
+        let time = Duration::from_millis(u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as u64);
+        let universe = u16::from_le_bytes([header[4], header[5]]);
+        let mut channels = self.frames.get(&universe).copied().unwrap_or([0; 512]);
+        match header[6] {
+            FRAME_FULL => self.file.read_exact(&mut channels)?,
+            FRAME_DELTA => {
+                let mut count = [0; 2];
+                self.file.read_exact(&mut count)?;
+                for _ in 0..u16::from_le_bytes(count) {
+                    let mut change = [0; 3];
+                    self.file.read_exact(&mut change)?;
+                    let index = u16::from_le_bytes([change[0], change[1]]) as usize;
+                    if index >= channels.len() {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, "Channel index out of range"));
+                    }
+                    channels[index] = change[2];
+                }
+            },
+            kind => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown frame kind {}", kind))),
+        }
+        self.frames.insert(universe, channels);
+        Ok(Some(ShowFrame { time, universe, channels }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_written_frames() {
+        let path = std::env::temp_dir().join(format!("artnet_to_opendmx_show_{}.show", std::process::id()));
+        let mut first = [0; 512];
+        first[0] = 255;
+        let mut second = first;
+        second[10] = 20;
+        let full = [7; 512];
+        let mut writer = ShowWriter::create(&path).unwrap();
+        writer.write_frame(1, &first).unwrap();
+        writer.write_frame(2, &full).unwrap();
+        writer.write_frame(1, &second).unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = ShowReader::open(&path).unwrap();
+        for _ in 0..2 {
+            let frames = std::iter::from_fn(|| reader.next_frame().unwrap()).map(|frame| (frame.universe, frame.channels)).collect::<Vec<_>>();
+            assert_eq!(frames, vec![(1, first), (2, full), (1, second)]);
+            reader.rewind().unwrap();
+        }
+        // One full frame per universe and a delta with a single change
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 8 + 2 * (7 + 512) + 7 + 2 + 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn ends_with_the_last_complete_frame() {
+        let path = std::env::temp_dir().join(format!("artnet_to_opendmx_truncated_{}.show", std::process::id()));
+        let mut writer = ShowWriter::create(&path).unwrap();
+        writer.write_frame(1, &[1; 512]).unwrap();
+        writer.write_frame(1, &[2; 512]).unwrap();
+        writer.finish().unwrap();
+        let data = std::fs::read(&path).unwrap();
+        for cut in [1, 300, 512 + 6] {
+            std::fs::write(&path, &data[..data.len() - cut]).unwrap();
+            let mut reader = ShowReader::open(&path).unwrap();
+            assert_eq!(reader.next_frame().unwrap().map(|frame| frame.channels), Some([1; 512]));
+            assert!(reader.next_frame().unwrap().is_none());
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = std::env::temp_dir().join(format!("artnet_to_opendmx_not_a_show_{}.show", std::process::id()));
+        std::fs::write(&path, b"A2DSHOW\x02").unwrap();
+        assert_eq!(ShowReader::open(&path).err().map(|error| error.kind()), Some(io::ErrorKind::InvalidData));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
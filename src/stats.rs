@@ -0,0 +1,185 @@
+//! Runtime statistics of the runner
+use std::{collections::HashMap, fmt::{Display, Formatter}, net::IpAddr, time::{Duration, Instant}};
+
+use crate::runner::Protocol;
+
+pub const RATE_INTERVAL: Duration = Duration::from_secs(1);
+///Senders which haven't sent anything for this long are dropped from the statistics
+const SENDER_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statistics {
+    ///Recieved dmx packets (Art-Net and sACN) per second
+    pub packets_per_second: f32,
+    ///Frames written to the dmx interfaces per second
+    pub frames_per_second: f32,
+    pub packets: u64,
+    pub frames: u64,
+    ///Packets for universes without a route
+    pub other_universe_packets: u64,
+    ///Packets from senders which aren't allowed controllers
+    pub rejected_packets: u64,
+    pub serial_failures: u64,
+    pub reconnects: u64,
+    ///Every sender of the last minute, most packets first
+    pub senders: Vec<SenderStatistics>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SenderStatistics {
+    pub ip: IpAddr,
+    pub protocol: Protocol,
+    pub packets: u64,
+    pub packets_per_second: f32,
+    ///The sender isn't an allowed controller
+    pub rejected: bool,
+}
+
+///The counters of one dmx interface
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputCounters {
+    pub frames: u64,
+    pub serial_failures: u64,
+    pub reconnects: u64,
+}
+
+impl std::ops::Add for OutputCounters {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            frames: self.frames + other.frames,
+            serial_failures: self.serial_failures + other.serial_failures,
+            reconnects: self.reconnects + other.reconnects,
+        }
+    }
+}
+
+struct SenderCounter {
+    rejected: bool,
+    packets: u64,
+    window_packets: u64,
+    last_seen: Instant,
+}
+
+pub struct StatisticsCollector {
+    statistics: Statistics,
+    window_start: Instant,
+    window_packets: u64,
+    window_frames: u64,
+    senders: HashMap<(IpAddr, Protocol), SenderCounter>,
+}
+
+impl StatisticsCollector {
+    pub fn new() -> Self {
+        Self {
+            statistics: Statistics::default(),
+            window_start: Instant::now(),
+            window_packets: 0,
+            window_frames: 0,
+            senders: HashMap::new(),
+        }
+    }
+
+    ///Counts a recieved dmx packet, `routed` is `false` if no route listens to its universe.
+    ///Frames of a played back show weren't recieved, they aren't counted.
+    pub fn packet(&mut self, sender: IpAddr, protocol: Protocol, routed: bool) {
+        if protocol == Protocol::Playback {
+            return;
+        }
+        self.statistics.packets += 1;
+        if !routed {
+            self.statistics.other_universe_packets += 1;
+        }
+        self.count(sender, protocol, false, 1);
+    }
+
+    ///Counts the `packets` of a sender which isn't an allowed controller. Returns `true` if the sender is new.
+    pub fn rejected(&mut self, sender: IpAddr, protocol: Protocol, packets: u64) -> bool {
+        self.statistics.rejected_packets += packets;
+        self.count(sender, protocol, true, packets)
+    }
+
+    fn count(&mut self, sender: IpAddr, protocol: Protocol, rejected: bool, packets: u64) -> bool {
+        let new = !self.senders.contains_key(&(sender, protocol));
+        let counter = self.senders.entry((sender, protocol)).or_insert(SenderCounter {
+            rejected,
+            packets: 0,
+            window_packets: 0,
+            last_seen: Instant::now(),
+        });
+        counter.packets += packets;
+        counter.window_packets += packets;
+        counter.last_seen = Instant::now();
+        new
+    }
+
+    ///Recalculates the rates once every [`RATE_INTERVAL`]. Returns `true` if it did.
+    pub fn update(&mut self, outputs: OutputCounters) -> bool {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < RATE_INTERVAL {
+            return false;
+        }
+        let seconds = elapsed.as_secs_f32();
+        let statistics = &mut self.statistics;
+        statistics.packets_per_second = (statistics.packets - self.window_packets) as f32 / seconds;
+        statistics.frames_per_second = (outputs.frames - self.window_frames) as f32 / seconds;
+        statistics.frames = outputs.frames;
+        statistics.serial_failures = outputs.serial_failures;
+        statistics.reconnects = outputs.reconnects;
+
+        self.senders.retain(|_, counter| counter.last_seen.elapsed() < SENDER_TIMEOUT);
+        statistics.senders = self.senders.iter_mut().map(|((ip, protocol), counter)| {
+            let packets_per_second = counter.window_packets as f32 / seconds;
+            counter.window_packets = 0;
+            SenderStatistics {
+                ip: *ip,
+                protocol: *protocol,
+                packets: counter.packets,
+                packets_per_second,
+                rejected: counter.rejected,
+            }
+        }).collect();
+        statistics.senders.sort_by_key(|sender| std::cmp::Reverse(sender.packets));
+
+        self.window_start = Instant::now();
+        self.window_packets = statistics.packets;
+        self.window_frames = outputs.frames;
+        true
+    }
+
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+}
+
+impl Display for Statistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1} packets/s, {:.1} frames/s, {} packets for other universes, {} rejected packets, {} serial failures, {} reconnects",
+            self.packets_per_second, self.frames_per_second, self.other_universe_packets, self.rejected_packets, self.serial_failures, self.reconnects)?;
+        for sender in &self.senders {
+            write!(f, "\n  {} ({}): {} packets, {:.1}/s{}", sender.ip, sender.protocol, sender.packets, sender.packets_per_second, if sender.rejected { " (rejected)" } else { "" })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_only_recieved_packets() {
+        let mut collector = StatisticsCollector::new();
+        let sender = IpAddr::from([2, 0, 0, 10]);
+        collector.packet(sender, Protocol::Artnet, true);
+        collector.packet(sender, Protocol::Sacn, false);
+        collector.packet(IpAddr::from([127, 0, 0, 1]), Protocol::Playback, true);
+        assert_eq!(collector.statistics.packets, 2);
+        assert_eq!(collector.statistics.other_universe_packets, 1);
+        assert_eq!(collector.senders.len(), 2);
+        assert!(collector.rejected(IpAddr::from([2, 0, 0, 11]), Protocol::Artnet, 5));
+        assert_eq!(collector.statistics.rejected_packets, 5);
+        assert!(!collector.rejected(IpAddr::from([2, 0, 0, 11]), Protocol::Artnet, 1));
+    }
+}
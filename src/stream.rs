@@ -0,0 +1,160 @@
+//! The WebSocket stream of the HTTP API, pushing the frames and the state of the runner
+use std::{io::{ErrorKind, Read, Write}, ops::RangeInclusive, str::FromStr, sync::{atomic::{AtomicBool, Ordering}, Mutex}, time::{Duration, Instant}};
+
+use log::{debug, info};
+use percent_encoding::percent_decode_str;
+use serde_json::{json, Map, Value};
+use tungstenite::{protocol::Role, Message, WebSocket};
+
+use crate::api::{ApiState, Slot};
+use crate::patch;
+
+const DEFAULT_RATE: f32 = 20.0;
+///The runner sends its updates at most every [`crate::runner::UPDATE_INTERVAL`], more wouldn't add anything
+const MAX_RATE: f32 = 20.0;
+///The most clients streaming at once from one server
+pub const MAX_CLIENTS: usize = 16;
+
+///What a client wants to recieve
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscription {
+    channels: Vec<RangeInclusive<u16>>,
+    ///Only send the channels which changed
+    diff: bool,
+    interval: Duration,
+}
+
+impl Default for Subscription {
+    fn default() -> Self {
+        Self {
+            channels: vec![1..=512],
+            diff: false,
+            interval: Duration::from_secs_f32(1.0 / DEFAULT_RATE),
+        }
+    }
+}
+
+impl Subscription {
+    ///The subscribed channels (0 based), each one once
+    fn indices(&self) -> Vec<usize> {
+        let mut indices = self.channels.iter().cloned().flatten().map(|channel| channel as usize - 1).collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+}
+
+impl FromStr for Subscription {
+    type Err = String;
+
+    ///Parses the query of the request, e.g. `channels=1-16,100&mode=diff&rate=10`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut subscription = Subscription::default();
+        for parameter in s.split('&').filter(|parameter| !parameter.is_empty()) {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            let decode = |text: &str| percent_decode_str(text).decode_utf8().map(|text| text.into_owned()).map_err(|_| format!("Invalid parameter \"{parameter}\""));
+            let (name, value) = (decode(name)?, decode(value)?);
+            match name.as_str() {
+                "channels" => subscription.channels = value.split(',').map(patch::parse_range).collect::<Result<_, _>>()?,
+                "mode" => subscription.diff = match value.as_str() {
+                    "full" => false,
+                    "diff" => true,
+                    _ => return Err(format!("Invalid mode \"{value}\" (expected full or diff)")),
+                },
+                "rate" => match value.parse::<f32>() {
+                    Ok(rate) if rate > 0.0 && rate.is_finite() => subscription.interval = Duration::from_secs_f32(1.0 / rate.min(MAX_RATE)),
+                    _ => return Err(format!("Invalid rate \"{value}\"")),
+                },
+                _ => return Err(format!("Unknown parameter \"{name}\"")),
+            }
+        }
+        Ok(subscription)
+    }
+}
+
+///Streams to an upgraded connection until the client goes away or the server stops
+///
+///Reads of `stream` have to time out, in between the frames get sent.
+pub fn serve<S: Read + Write>(stream: S, client: Slot, subscription: Subscription, state: &Mutex<ApiState>, running: &AtomicBool) {
+    let _client = client;
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    let indices = subscription.indices();
+    let mut sent_frames: Vec<(u16, [u8; 512])> = Vec::new();
+    let mut sent_status = Value::Null;
+    let mut last_sent: Option<Instant> = None;
+    info!("Stream client connected");
+    while running.load(Ordering::Relaxed) {
+        if last_sent.is_none_or(|instant| instant.elapsed() >= subscription.interval) {
+            last_sent = Some(Instant::now());
+            let (frames, status) = match state.lock() {
+                Ok(state) => (state.frames(), state.status()),
+                Err(_) => break,
+            };
+            let mut messages = Vec::new();
+            if status != sent_status {
+                let mut message = json!({ "type": "status" });
+                message.as_object_mut().unwrap().extend(status.as_object().cloned().unwrap_or_default());
+                messages.push(message);
+                sent_status = status;
+            }
+            let routes = frames.iter().enumerate().filter_map(|(i, (universe, channels))| {
+                let previous = sent_frames.get(i).filter(|(sent_universe, _)| sent_universe == universe).map(|(_, channels)| channels);
+                let changed = |index: &usize| previous.is_none_or(|previous| previous[*index] != channels[*index]);
+                if !indices.iter().any(changed) {
+                    return None;
+                }
+                let values = indices.iter().filter(|index| !subscription.diff || changed(index))
+                    .map(|index| ((index + 1).to_string(), Value::from(channels[*index])))
+                    .collect::<Map<_, _>>();
+                Some(json!({ "universe": universe, "channels": values }))
+            }).collect::<Vec<_>>();
+            if !routes.is_empty() {
+                messages.push(json!({ "type": "frame", "routes": routes }));
+            }
+            sent_frames = frames;
+            if let Some(error) = messages.into_iter().find_map(|message| socket.send(Message::Text(message.to_string())).err()) {
+                debug!("Stream client gone: {}", error);
+                break;
+            }
+        }
+        // Waits for the client until the read times out, pings get answered on the way
+        match socket.read() {
+            Ok(Message::Close(_)) => {
+                debug!("Stream client closed the connection");
+                break;
+            },
+            Ok(_) => {},
+            Err(tungstenite::Error::Io(error)) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {},
+            Err(error) => {
+                debug!("Stream client gone: {}", error);
+                break;
+            },
+        }
+    }
+    let _ = socket.close(None);
+    let _ = socket.flush();
+    info!("Stream client disconnected");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_encoded_queries() {
+        let subscription = "channels=1-3%2C10&mode=diff&rate=100".parse::<Subscription>().unwrap();
+        assert_eq!(subscription.indices(), [0, 1, 2, 9]);
+        assert!(subscription.diff);
+        // Capped to the rate of the runner
+        assert_eq!(subscription.interval, Duration::from_secs_f32(1.0 / MAX_RATE));
+        assert_eq!("".parse::<Subscription>().unwrap(), Subscription::default());
+    }
+
+    #[test]
+    fn rejects_invalid_queries() {
+        assert!("channels=%FF".parse::<Subscription>().is_err());
+        assert!("mode=some".parse::<Subscription>().is_err());
+        assert!("rate=0".parse::<Subscription>().is_err());
+        assert!("volume=11".parse::<Subscription>().is_err());
+    }
+}
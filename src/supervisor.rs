@@ -0,0 +1,169 @@
+//! Keeps the dmx output of a route connected
+use std::{fmt::{Display, Formatter}, time::{Duration, Instant}};
+
+use log::{debug, info, warn};
+use serialport::available_ports;
+
+use crate::device::DeviceSelector;
+use crate::output::{self, DmxOutput, OutputError, OutputKind};
+
+///How often a missing device is looked for
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceState {
+    ///The device didn't show up since the start
+    #[default]
+    Waiting,
+    Connected,
+    ///The device got disconnected and didn't come back yet
+    Lost,
+}
+
+impl Display for DeviceState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceState::Waiting => write!(f, "Waiting"),
+            DeviceState::Connected => write!(f, "Connected"),
+            DeviceState::Lost => write!(f, "Lost"),
+        }
+    }
+}
+
+///The output of a route, opened again whenever it's missing
+pub struct DeviceSupervisor {
+    kind: OutputKind,
+    ///The configured device name
+    name: String,
+    ///Devices are looked up in the port list, the other outputs are opened by name
+    selector: Option<DeviceSelector>,
+    dmx: Option<Box<dyn DmxOutput>>,
+    state: DeviceState,
+    ///The current frame, replayed on reconnect. `None` until the first one got written.
+    channels: Option<[u8; 512]>,
+    break_time: Option<Duration>,
+    ///Keep the output refreshing on its own
+    refresh: bool,
+    last_attempt: Instant,
+}
+
+impl DeviceSupervisor {
+    ///Doesn't open the output yet, see [`DeviceSupervisor::connect`]
+    pub fn new(kind: OutputKind, name: &str, selector: Option<DeviceSelector>, break_time: Option<Duration>, refresh: bool) -> Self {
+        Self {
+            kind,
+            name: name.into(),
+            selector,
+            dmx: None,
+            state: DeviceState::Waiting,
+            channels: None,
+            break_time,
+            refresh,
+            last_attempt: Instant::now(),
+        }
+    }
+
+    ///Looks for the device and opens it. [`OutputError::NotFound`] if it isn't there (yet).
+    pub fn connect(&mut self) -> Result<(), OutputError> {
+        self.last_attempt = Instant::now();
+        let name = match &self.selector {
+            Some(selector) => selector.resolve(&available_ports()?).ok_or_else(|| OutputError::NotFound(self.name.clone()))?,
+            None => self.name.clone(),
+        };
+        let mut dmx = output::open(self.kind, &name)?;
+        if let Some(time) = self.break_time {
+            debug!("Setting dmx interface break time to {}ms", time.as_millis());
+            dmx.set_packet_time(time);
+        }
+        dmx.set_channels(self.get_channels());
+        if self.refresh {
+            debug!("Setting dmx interface to refresh continuously");
+            dmx.set_async()?;
+        } else if self.channels.is_some() {
+            dmx.update()?;
+        }
+        if name != self.name {
+            info!("Found device \"{}\" at \"{}\"", self.name, name);
+        }
+        self.dmx = Some(dmx);
+        self.state = DeviceState::Connected;
+        Ok(())
+    }
+
+    ///Notices a disconnected device and retries a missing one. `true` if it just got connected.
+    pub fn poll(&mut self) -> bool {
+        if let Some(dmx) = &self.dmx {
+            if dmx.is_connected() {
+                return false;
+            }
+            self.lose();
+        }
+        if self.last_attempt.elapsed() < RETRY_INTERVAL {
+            return false;
+        }
+        let state = self.state;
+        match self.connect() {
+            Ok(()) => {
+                match state {
+                    DeviceState::Lost => info!("Device \"{}\" is back, replaying the current frame", self.name),
+                    _ => info!("Device \"{}\" connected", self.name),
+                }
+                true
+            },
+            Err(error) => {
+                debug!("Device \"{}\" still unavailable: {}", self.name, error);
+                false
+            },
+        }
+    }
+
+    fn lose(&mut self) {
+        if let Some(mut dmx) = self.dmx.take() {
+            warn!("Lost device \"{}\", waiting for it to come back...", dmx.name());
+            // Lets the interface thread release the port right away
+            dmx.set_sync();
+        }
+        self.state = DeviceState::Lost;
+    }
+
+    ///Sends `channels`. `false` if the device is missing, they are sent once it's back.
+    pub fn write(&mut self, channels: [u8; 512]) -> Result<bool, OutputError> {
+        self.channels = Some(channels);
+        let Some(dmx) = &mut self.dmx else {
+            return Ok(false);
+        };
+        dmx.set_channels(channels);
+        if let Err(error) = dmx.update() {
+            self.lose();
+            return Err(error);
+        }
+        Ok(true)
+    }
+
+    ///The name of the opened port, the configured name while there is none
+    pub fn name(&self) -> &str {
+        match &self.dmx {
+            Some(dmx) => dmx.name(),
+            None => &self.name,
+        }
+    }
+
+    pub fn get_channels(&self) -> [u8; 512] {
+        self.channels.unwrap_or([0; 512])
+    }
+
+    pub fn state(&self) -> DeviceState {
+        self.state
+    }
+
+    ///Missing devices count as synchronous, nothing refreshes them
+    pub fn is_sync(&self) -> bool {
+        self.dmx.as_ref().is_none_or(|dmx| dmx.is_sync())
+    }
+
+    pub fn set_sync(&mut self) {
+        if let Some(dmx) = &mut self.dmx {
+            dmx.set_sync();
+        }
+    }
+}
@@ -0,0 +1,543 @@
+//! Drives the bridge end-to-end with the outputs that need no hardware
+use std::{fs, io::{Read, Write}, net::{TcpListener, TcpStream, UdpSocket}, path::PathBuf, process::{Child, Command, Stdio}, sync::mpsc, thread, time::{Duration, Instant}};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+///A running bridge, killed when dropped
+struct Bridge(Child);
+
+impl Bridge {
+    fn start(args: &[&str]) -> Self {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_artnet_to_opendmx"));
+        command.args(args).args(["--nogui"]);
+        if !args.contains(&"--config") {
+            // Don't pick up the config of the user running the tests
+            command.arg("--config").arg(std::env::temp_dir().join("artnet_to_opendmx_missing_config.toml"));
+        }
+        let child = command
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Couldn't start the bridge");
+        Bridge(child)
+    }
+
+    ///Sends a termination signal and waits for the bridge to exit
+    #[cfg(unix)]
+    fn terminate(&mut self) -> std::process::ExitStatus {
+        let status = Command::new("kill").args(["-TERM", &self.0.id().to_string()]).status().unwrap();
+        assert!(status.success());
+        let start = Instant::now();
+        loop {
+            if let Some(status) = self.0.try_wait().unwrap() {
+                return status;
+            }
+            assert!(start.elapsed() < TIMEOUT, "Bridge didn't stop");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+///A fresh directory for the files of one test
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("artnet_to_opendmx_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn free_port() -> u16 {
+    UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn free_tcp_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+///Sends one request to the HTTP API, retrying until it's up. Returns the status code and body.
+fn http(port: u16, method: &str, path: &str, body: &str) -> (u16, String) {
+    http_with_headers(port, method, path, "", body)
+}
+
+///Like [`http`], `headers` are complete lines like `Authorization: Bearer token\r\n`
+fn http_with_headers(port: u16, method: &str, path: &str, headers: &str, body: &str) -> (u16, String) {
+    let start = Instant::now();
+    let mut stream = loop {
+        match TcpStream::connect(("127.0.0.1", port)) {
+            Ok(stream) => break stream,
+            Err(_) if start.elapsed() < TIMEOUT => thread::sleep(Duration::from_millis(50)),
+            Err(error) => panic!("HTTP API unreachable: {error}"),
+        }
+    };
+    write!(stream, "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{headers}Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response.split(' ').nth(1).and_then(|status| status.parse().ok()).expect("Invalid HTTP response");
+    let body = response.split_once("\r\n\r\n").map(|(_, body)| body.to_string()).unwrap_or_default();
+    (status, body)
+}
+
+fn art_dmx(universe: u16, channels: &[u8; 512]) -> Vec<u8> {
+    let mut packet = b"Art-Net\0".to_vec();
+    packet.extend_from_slice(&0x5000u16.to_le_bytes());
+    packet.extend_from_slice(&14u16.to_be_bytes());
+    packet.extend_from_slice(&[0, 0]); // sequence, physical
+    packet.extend_from_slice(&universe.to_le_bytes());
+    packet.extend_from_slice(&512u16.to_be_bytes());
+    packet.extend_from_slice(channels);
+    packet
+}
+
+///An OSC message with one argument, `tag` being its type (`i` or `f`)
+fn osc(address: &str, tag: char, argument: [u8; 4]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    for string in [address.to_string(), format!(",{tag}")] {
+        packet.extend_from_slice(string.as_bytes());
+        packet.resize((packet.len() / 4 + 1) * 4, 0);
+    }
+    packet.extend_from_slice(&argument);
+    packet
+}
+
+fn channels(values: &[(usize, u8)]) -> [u8; 512] {
+    let mut channels = [0; 512];
+    for (index, value) in values {
+        channels[*index] = *value;
+    }
+    channels
+}
+
+///The last frame of a file output
+fn last_frame(path: &PathBuf) -> Option<[u8; 512]> {
+    let data = fs::read(path).ok()?;
+    data.len().checked_sub(512).map(|start| data[start..start + 512].try_into().unwrap())
+}
+
+///Calls `send` until the file output shows `expected`
+fn wait_for_frame(path: &PathBuf, expected: &[u8; 512], mut send: impl FnMut()) {
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        send();
+        thread::sleep(Duration::from_millis(50));
+        if last_frame(path).as_ref() == Some(expected) {
+            return;
+        }
+    }
+    panic!("Output never showed the expected frame, last frame: {:?}", last_frame(path).map(|frame| frame[..8].to_vec()));
+}
+
+#[test]
+fn artnet_to_file() {
+    let dir = test_dir("artnet_to_file");
+    let output = dir.join("out.dmx");
+    let port = free_port();
+    let _bridge = Bridge::start(&["3", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-p", &port.to_string()]);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let expected = channels(&[(0, 255), (1, 128), (511, 7)]);
+    wait_for_frame(&output, &expected, || {
+        // Other universes must not reach the output
+        socket.send_to(&art_dmx(4, &[99; 512]), ("127.0.0.1", port)).unwrap();
+        socket.send_to(&art_dmx(3, &expected), ("127.0.0.1", port)).unwrap();
+    });
+}
+
+#[test]
+fn merges_two_senders_htp() {
+    let dir = test_dir("merges_two_senders_htp");
+    let output = dir.join("out.dmx");
+    let port = free_port();
+    let _bridge = Bridge::start(&["0", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-p", &port.to_string()]);
+
+    let first = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let second = UdpSocket::bind("127.0.0.1:0").unwrap();
+    wait_for_frame(&output, &channels(&[(0, 200), (1, 100), (2, 50)]), || {
+        first.send_to(&art_dmx(0, &channels(&[(0, 200), (1, 10)])), ("127.0.0.1", port)).unwrap();
+        second.send_to(&art_dmx(0, &channels(&[(0, 20), (1, 100), (2, 50)])), ("127.0.0.1", port)).unwrap();
+    });
+}
+
+#[test]
+fn patches_channels() {
+    let dir = test_dir("patches_channels");
+    let output = dir.join("out.dmx");
+    let patch = dir.join("patch.txt");
+    fs::write(&patch, "10 -> 1, 2\n").unwrap();
+    let port = free_port();
+    let _bridge = Bridge::start(&["0", output.to_str().unwrap(), "--output", "file", "--patch", patch.to_str().unwrap(), "--bind-ip", "127.0.0.1", "-p", &port.to_string()]);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    wait_for_frame(&output, &channels(&[(0, 42), (1, 42), (9, 42)]), || {
+        socket.send_to(&art_dmx(0, &channels(&[(9, 42)])), ("127.0.0.1", port)).unwrap();
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn records_until_terminated() {
+    let dir = test_dir("records_until_terminated");
+    let show = dir.join("recording.dmxshow");
+    let port = free_port();
+    let mut bridge = Bridge::start(&["record", show.to_str().unwrap(), "2", "--bind-ip", "127.0.0.1", "-p", &port.to_string()]);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let start = Instant::now();
+    // The header and at least one frame
+    while fs::metadata(&show).map_or(0, |metadata| metadata.len()) <= 8 {
+        assert!(start.elapsed() < TIMEOUT, "Nothing got recorded");
+        socket.send_to(&art_dmx(2, &channels(&[(0, 255)])), ("127.0.0.1", port)).unwrap();
+        thread::sleep(Duration::from_millis(50));
+    }
+    let status = bridge.terminate();
+    assert!(status.success(), "Recording failed: {status}");
+    assert!(fs::read(&show).unwrap().starts_with(b"A2DSHOW\x01"));
+}
+
+#[cfg(unix)]
+#[test]
+fn finishes_recording_of_running_bridge_when_terminated() {
+    let dir = test_dir("finishes_recording_of_running_bridge_when_terminated");
+    let output = dir.join("out.dmx");
+    let show = dir.join("recording.dmxshow");
+    for api in [false, true] {
+        let port = free_port().to_string();
+        let http_address = format!("127.0.0.1:{}", free_tcp_port());
+        let mut args = vec!["0", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-p", &port, "--record", show.to_str().unwrap()];
+        if api {
+            args.extend(["--http", &http_address]);
+        }
+        let port = port.parse::<u16>().unwrap();
+        let mut bridge = Bridge::start(&args);
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        wait_for_frame(&output, &channels(&[(0, 255)]), || {
+            socket.send_to(&art_dmx(0, &channels(&[(0, 255)])), ("127.0.0.1", port)).unwrap();
+        });
+        let status = bridge.terminate();
+        assert!(status.success(), "Bridge failed: {status}");
+        // The header and at least the first full frame got flushed
+        let data = fs::read(&show).unwrap();
+        assert!(data.starts_with(b"A2DSHOW\x01") && data.len() >= 8 + 7 + 512, "{} bytes recorded", data.len());
+        fs::remove_file(&show).unwrap();
+        fs::remove_file(&output).unwrap();
+    }
+}
+
+// The whole 127.0.0.0/8 network is on the loopback interface on Linux
+#[cfg(target_os = "linux")]
+#[test]
+fn listens_to_allowed_controllers_only() {
+    let dir = test_dir("listens_to_allowed_controllers_only");
+    let output = dir.join("out.dmx");
+    let port = free_port();
+    let osc_port = free_port();
+    let _bridge = Bridge::start(&["0", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-c", "127.0.0.2,127.0.1.0/24", "-p", &port.to_string(), "--osc", &osc_port.to_string()]);
+
+    let main = UdpSocket::bind("127.0.0.2:0").unwrap();
+    let backup = UdpSocket::bind("127.0.1.7:0").unwrap();
+    let other = UdpSocket::bind("127.0.0.3:0").unwrap();
+    // Merged with the other sender or blacked out by it, the output would never be 1
+    wait_for_frame(&output, &channels(&[(0, 1), (1, 2)]), || {
+        other.send_to(&art_dmx(0, &[99; 512]), ("127.0.0.1", port)).unwrap();
+        other.send_to(&osc("/artnet2dmx/blackout", 'i', 1i32.to_be_bytes()), ("127.0.0.1", osc_port)).unwrap();
+        main.send_to(&art_dmx(0, &channels(&[(0, 1)])), ("127.0.0.1", port)).unwrap();
+        backup.send_to(&art_dmx(0, &channels(&[(1, 2)])), ("127.0.0.1", port)).unwrap();
+    });
+}
+
+#[test]
+fn runs_config_file() {
+    let dir = test_dir("runs_config_file");
+    let output = dir.join("out.dmx");
+    let config = dir.join("config.toml");
+    let port = free_port();
+    // The command line overrides the port of the file
+    fs::write(&config, format!("bind_ip = \"127.0.0.1\"\nport = 1\noutput = \"file\"\npatch = \"1 -> 2\"\n\n[[routes]]\nuniverse = 5\ndevice = {:?}\n", output.to_str().unwrap())).unwrap();
+    let _bridge = Bridge::start(&["--config", config.to_str().unwrap(), "-p", &port.to_string()]);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    wait_for_frame(&output, &channels(&[(0, 9), (1, 9)]), || {
+        socket.send_to(&art_dmx(5, &channels(&[(0, 9)])), ("127.0.0.1", port)).unwrap();
+    });
+}
+
+#[test]
+fn plays_show_file() {
+    let dir = test_dir("plays_show_file");
+    let output = dir.join("out.dmx");
+    let show = dir.join("show.dmxshow");
+    let mut data = b"A2DSHOW\x01".to_vec();
+    // A full frame at 0ms, then a delta at 100ms setting channel 2 to 77
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.push(0);
+    data.extend_from_slice(&channels(&[(0, 10)]));
+    data.extend_from_slice(&100u32.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.push(77);
+    fs::write(&show, data).unwrap();
+    let _bridge = Bridge::start(&["play", show.to_str().unwrap(), "1", output.to_str().unwrap(), "--output", "file", "--loss", "blackout:0"]);
+
+    let look = channels(&[(0, 10), (1, 77)]);
+    wait_for_frame(&output, &look, || {});
+    // A finished show holds its look past the signal timeout
+    thread::sleep(Duration::from_secs(5));
+    assert_eq!(last_frame(&output), Some(look));
+}
+
+#[cfg(unix)]
+#[test]
+fn artnet_to_pty() {
+    let dir = test_dir("artnet_to_pty");
+    let device = dir.join("dmx0");
+    let port = free_port();
+    let _bridge = Bridge::start(&["0", device.to_str().unwrap(), "--output", "pty", "--bind-ip", "127.0.0.1", "-p", &port.to_string()]);
+
+    let start = Instant::now();
+    while !device.exists() {
+        assert!(start.elapsed() < TIMEOUT, "Pseudo-terminal never got linked");
+        thread::sleep(Duration::from_millis(20));
+    }
+    let mut terminal = fs::File::open(&device).unwrap();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut packet = [0; 513];
+        while terminal.read_exact(&mut packet).is_ok() {
+            if tx.send(packet).is_err() {
+                break;
+            }
+        }
+    });
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let expected = channels(&[(0, 1), (255, 2), (511, 3)]);
+    socket.send_to(&art_dmx(0, &expected), ("127.0.0.1", port)).unwrap();
+    let packet = rx.recv_timeout(TIMEOUT).expect("No data on the pseudo-terminal");
+    assert_eq!(packet[0], 0, "start code");
+    assert_eq!(packet[1..], expected);
+}
+
+#[test]
+fn waits_for_missing_device() {
+    let dir = test_dir("waits_for_missing_device");
+    let later = dir.join("later");
+    let output = later.join("out.dmx");
+    let port = free_port();
+    let mut bridge = Bridge::start(&["0", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-p", &port.to_string()]);
+
+    // A device that isn't there yet must not stop the bridge
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let expected = channels(&[(0, 99), (511, 1)]);
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(1500) {
+        socket.send_to(&art_dmx(0, &expected), ("127.0.0.1", port)).unwrap();
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert!(bridge.0.try_wait().unwrap().is_none(), "Bridge exited while waiting for the device");
+    assert!(!output.exists());
+
+    // Once it shows up it gets the current frame without another packet
+    fs::create_dir(&later).unwrap();
+    wait_for_frame(&output, &expected, || {});
+}
+
+#[test]
+fn records_input_during_blackout() {
+    let dir = test_dir("records_input_during_blackout");
+    let output = dir.join("out.dmx");
+    let show = dir.join("show.dmxshow");
+    let port = free_port();
+    let http_port = free_tcp_port();
+    let _bridge = Bridge::start(&["0", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-p", &port.to_string(), "--http", &format!("127.0.0.1:{http_port}"), "--record", show.to_str().unwrap()]);
+
+    let start = Instant::now();
+    while http(http_port, "POST", "/api/blackout", "{\"enabled\": true}").0 != 200 {
+        assert!(start.elapsed() < TIMEOUT, "Blackout never got applied");
+        thread::sleep(Duration::from_millis(50));
+    }
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let expected = channels(&[(0, 255), (9, 17)]);
+    // The output stays black, the first recorded frame (a full one after the header) is the input
+    let start = Instant::now();
+    while fs::metadata(&show).map_or(0, |metadata| metadata.len()) < 8 + 7 + 512 {
+        assert!(start.elapsed() < TIMEOUT, "Show file never got written");
+        socket.send_to(&art_dmx(0, &expected), ("127.0.0.1", port)).unwrap();
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(last_frame(&output), Some([0; 512]));
+    assert_eq!(fs::read(&show).unwrap()[15..15 + 512], expected);
+}
+
+#[test]
+fn http_api_status_and_blackout() {
+    let dir = test_dir("http_api_status_and_blackout");
+    let output = dir.join("out.dmx");
+    let port = free_port();
+    let http_port = free_tcp_port();
+    let _bridge = Bridge::start(&["0", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-p", &port.to_string(), "--http", &format!("127.0.0.1:{http_port}")]);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let expected = channels(&[(0, 255), (4, 42)]);
+    wait_for_frame(&output, &expected, || {
+        socket.send_to(&art_dmx(0, &expected), ("127.0.0.1", port)).unwrap();
+    });
+    let (status, body) = http(http_port, "GET", "/api/status", "");
+    assert_eq!(status, 200);
+    assert!(body.contains("\"running\":true"), "{body}");
+    let start = Instant::now();
+    while !http(http_port, "GET", "/api/frame", "").1.contains("[255,0,0,0,42,") {
+        assert!(start.elapsed() < TIMEOUT, "Frame never showed up in the API");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    assert_eq!(http(http_port, "POST", "/api/blackout", "{\"enabled\": true}").0, 200);
+    wait_for_frame(&output, &[0; 512], || {
+        socket.send_to(&art_dmx(0, &expected), ("127.0.0.1", port)).unwrap();
+    });
+    assert_eq!(http(http_port, "POST", "/api/blackout", "{\"enabled\": false}").0, 200);
+    wait_for_frame(&output, &expected, || {
+        socket.send_to(&art_dmx(0, &expected), ("127.0.0.1", port)).unwrap();
+    });
+
+    // Paths on the machine and the API address can't be changed remotely
+    let (status, config) = http(http_port, "GET", "/api/config", "");
+    assert_eq!(status, 200);
+    let elsewhere = dir.join("elsewhere.dmx");
+    for (field, value) in [("device", elsewhere.to_str().unwrap()), ("loss", "scene:/etc/hostname"), ("http", "0.0.0.0:1")] {
+        let mut changed = serde_json::from_str::<serde_json::Value>(&config).unwrap();
+        match field {
+            "device" => changed["routes"][0]["device"] = value.into(),
+            _ => changed[field] = value.into(),
+        }
+        assert_eq!(http(http_port, "PUT", "/api/config", &changed.to_string()).0, 403, "{field}");
+    }
+    assert!(!elsewhere.exists());
+
+    assert_eq!(http(http_port, "POST", "/api/blackout", "on").0, 400);
+    assert_eq!(http(http_port, "DELETE", "/api/status", "").0, 405);
+    assert_eq!(http(http_port, "GET", "/api/unknown", "").0, 404);
+}
+
+#[test]
+fn requires_the_api_token() {
+    let dir = test_dir("requires_the_api_token");
+    let output = dir.join("out.dmx");
+    let port = free_port();
+    let http_port = free_tcp_port();
+    let _bridge = Bridge::start(&["0", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-p", &port.to_string(), "--http", &format!("127.0.0.1:{http_port}"), "--http-token", "s3cret"]);
+
+    assert_eq!(http(http_port, "GET", "/api/status", "").0, 401);
+    assert_eq!(http(http_port, "GET", "/metrics", "").0, 401);
+    assert_eq!(http_with_headers(http_port, "POST", "/api/stop", "Authorization: Bearer s3cre\r\n", "").0, 401);
+    assert_eq!(http_with_headers(http_port, "GET", "/api/status", "Authorization: Bearer s3cret\r\n", "").0, 200);
+    let (status, config) = http_with_headers(http_port, "GET", "/api/config", "Authorization: Bearer s3cret\r\n", "");
+    assert_eq!(status, 200);
+    assert!(!config.contains("s3cret"), "{config}");
+}
+
+#[test]
+fn streams_channel_diffs_over_websocket() {
+    let dir = test_dir("streams_channel_diffs_over_websocket");
+    let output = dir.join("out.dmx");
+    let port = free_port();
+    let http_port = free_tcp_port();
+    let _bridge = Bridge::start(&["0", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-p", &port.to_string(), "--http", &format!("127.0.0.1:{http_port}")]);
+    // Waits for the API
+    http(http_port, "GET", "/api/status", "");
+    assert_eq!(http(http_port, "GET", "/api/stream?mode=some", "").0, 426);
+    let handshake = "Upgrade: websocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n";
+    assert_eq!(http_with_headers(http_port, "GET", "/api/stream", &format!("{handshake}Sec-WebSocket-Version: 8\r\n"), "").0, 426);
+
+    let (mut stream, _) = tungstenite::connect(format!("ws://127.0.0.1:{http_port}/api/stream?channels=1-3,10&mode=diff")).unwrap();
+    if let tungstenite::stream::MaybeTlsStream::Plain(stream) = stream.get_ref() {
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+    }
+    // The first frame is complete, waiting for it keeps the channels sent below out of it
+    let start = Instant::now();
+    loop {
+        let message = stream.read().unwrap().into_text().unwrap();
+        if message.contains("\"type\":\"frame\"") {
+            assert!(message.contains("\"channels\":{\"1\":0,\"10\":0,\"2\":0,\"3\":0}"), "{message}");
+            break;
+        }
+        assert!(start.elapsed() < TIMEOUT, "Stream never sent a frame");
+    }
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    // Sends `channels` until a frame message contains `expected`
+    let mut wait_for_message = |channels: &[u8; 512], expected: &str| {
+        let start = Instant::now();
+        while start.elapsed() < TIMEOUT {
+            socket.send_to(&art_dmx(0, channels), ("127.0.0.1", port)).unwrap();
+            let message = stream.read().unwrap().into_text().unwrap();
+            if message.contains("\"type\":\"frame\"") && message.contains(expected) {
+                return;
+            }
+        }
+        panic!("Stream never sent {expected}");
+    };
+    // Later frames only have the changes
+    wait_for_message(&channels(&[(0, 255), (4, 42)]), "\"channels\":{\"1\":255}");
+    wait_for_message(&channels(&[(0, 255), (1, 7), (4, 42)]), "\"channels\":{\"2\":7}");
+}
+
+#[test]
+fn serves_prometheus_metrics() {
+    let dir = test_dir("serves_prometheus_metrics");
+    let output = dir.join("out.dmx");
+    let port = free_port();
+    let http_port = free_tcp_port();
+    let _bridge = Bridge::start(&["0", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-p", &port.to_string(), "--http", &format!("127.0.0.1:{http_port}")]);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let start = Instant::now();
+    let metrics = loop {
+        socket.send_to(&art_dmx(0, &channels(&[(0, 255)])), ("127.0.0.1", port)).unwrap();
+        socket.send_to(&art_dmx(1, &channels(&[(0, 255)])), ("127.0.0.1", port)).unwrap();
+        let (status, metrics) = http(http_port, "GET", "/metrics", "");
+        assert_eq!(status, 200);
+        // The totals are updated once a second
+        if metrics.contains("artnet2dmx_frames_sent_total") && !metrics.contains("artnet2dmx_frames_sent_total 0") {
+            break metrics;
+        }
+        assert!(start.elapsed() < TIMEOUT, "Metrics never counted a frame:\n{metrics}");
+        thread::sleep(Duration::from_millis(100));
+    };
+    let device = output.to_str().unwrap();
+    assert!(metrics.contains("artnet2dmx_up 1"), "{metrics}");
+    assert!(metrics.contains("artnet2dmx_packets_ignored_total{reason=\"other_universe\"} "), "{metrics}");
+    assert!(!metrics.contains("artnet2dmx_packets_ignored_total{reason=\"other_universe\"} 0"), "{metrics}");
+    assert!(metrics.contains(&format!("artnet2dmx_device_connected{{universe=\"0\",device=\"{device}\"}} 1")), "{metrics}");
+    assert!(metrics.contains(&format!("artnet2dmx_sender_info{{universe=\"0\",device=\"{device}\",sender=\"127.0.0.1\"}} 1")), "{metrics}");
+    assert!(metrics.contains(&format!("artnet2dmx_last_packet_age_seconds{{universe=\"0\",device=\"{device}\"}} ")), "{metrics}");
+}
+
+#[test]
+fn osc_overrides_channels() {
+    let dir = test_dir("osc_overrides_channels");
+    let output = dir.join("out.dmx");
+    let port = free_port();
+    let osc_port = free_port();
+    let _bridge = Bridge::start(&["0", output.to_str().unwrap(), "--output", "file", "--bind-ip", "127.0.0.1", "-p", &port.to_string(), "--osc", &osc_port.to_string()]);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let frame = channels(&[(0, 100), (1, 10)]);
+    let send = |message: &[u8]| {
+        socket.send_to(message, ("127.0.0.1", osc_port)).unwrap();
+        socket.send_to(&art_dmx(0, &frame), ("127.0.0.1", port)).unwrap();
+    };
+    wait_for_frame(&output, &channels(&[(0, 100), (1, 200)]), || send(&osc("/artnet2dmx/channel/2", 'i', 200i32.to_be_bytes())));
+    wait_for_frame(&output, &channels(&[(0, 50), (1, 100)]), || send(&osc("/artnet2dmx/master", 'f', 0.5f32.to_be_bytes())));
+    wait_for_frame(&output, &[0; 512], || send(&osc("/artnet2dmx/blackout", 'i', 1i32.to_be_bytes())));
+    send(&osc("/artnet2dmx/blackout", 'i', 0i32.to_be_bytes()));
+    send(&osc("/artnet2dmx/master", 'f', 1f32.to_be_bytes()));
+    wait_for_frame(&output, &frame, || send(&osc("/artnet2dmx/channel/2", 'i', (-1i32).to_be_bytes())));
+}